@@ -0,0 +1,118 @@
+use std::cell::RefCell;
+use std::io::{self, BufRead, BufReader, Cursor, Read};
+use std::mem;
+use std::rc::Rc;
+
+/// Compressed formats recognized by their magic bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+  Gzip,
+  Zstd,
+  Xz,
+  Bzip2,
+}
+
+/// The format `head`, the first bytes of an input, starts with, if any.
+/// Beyond the magic bytes, gzip must use deflate and bzip2 must have a
+/// block size and then a block or the end of the stream.
+pub fn detect(head: &[u8]) -> Option<Format> {
+  match head {
+    [0x1f, 0x8b, 0x08, ..] => Some(Format::Gzip),
+    [0x28, 0xb5, 0x2f, 0xfd, ..] => Some(Format::Zstd),
+    [0xfd, b'7', b'z', b'X', b'Z', 0x00, ..] => Some(Format::Xz),
+    [b'B', b'Z', b'h', b'1'..=b'9', rest @ ..]
+      if rest.starts_with(b"1AY&SY") || rest.starts_with(b"\x17rE8P\x90") =>
+    {
+      Some(Format::Bzip2)
+    }
+    _ => None,
+  }
+}
+
+/// Wrap `reader` in a decoder for `format`. Inputs made of several
+/// concatenated streams, as written by `cat a.gz b.gz`, are decoded whole.
+pub fn decoder<'a>(format: Format, reader: impl BufRead + 'a) -> io::Result<Box<dyn BufRead + 'a>> {
+  Ok(match format {
+    Format::Gzip => Box::new(BufReader::new(flate2::bufread::MultiGzDecoder::new(reader))),
+    Format::Zstd => Box::new(BufReader::new(zstd::Decoder::with_buffer(reader)?)),
+    Format::Xz => Box::new(BufReader::new(xz2::bufread::XzDecoder::new_multi_decoder(
+      reader,
+    ))),
+    Format::Bzip2 => Box::new(BufReader::new(bzip2::bufread::MultiBzDecoder::new(reader))),
+  })
+}
+
+/// The raw input while a decoder makes its first read. What the decoder
+/// takes is kept, so the input can be read again from the start.
+struct Probe {
+  input: Box<dyn BufRead>,
+  /// None once the first read has succeeded
+  taken: Option<Vec<u8>>,
+}
+
+#[derive(Clone)]
+struct Shared(Rc<RefCell<Probe>>);
+
+impl Read for Shared {
+  fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+    let mut probe = self.0.borrow_mut();
+    let n = probe.input.read(buf)?;
+    if let Some(taken) = &mut probe.taken {
+      taken.extend_from_slice(&buf[..n]);
+    }
+    Ok(n)
+  }
+}
+
+/// Decode `input` as `format`, or give it back unchanged when the decoder
+/// fails on its first read, as with a text file that merely starts with
+/// the same bytes
+pub fn decode(format: Format, input: Box<dyn BufRead>) -> Box<dyn BufRead> {
+  let shared = Shared(Rc::new(RefCell::new(Probe {
+    input,
+    taken: Some(Vec::new()),
+  })));
+  let decoded = decoder(format, BufReader::new(shared.clone())).and_then(|mut reader| {
+    reader.fill_buf()?;
+    Ok(reader)
+  });
+
+  let mut probe = shared.0.borrow_mut();
+  match decoded {
+    Ok(reader) => {
+      probe.taken = None;
+      reader
+    }
+    Err(_) => {
+      let taken = probe.taken.take().unwrap_or_default();
+      let input = mem::replace(&mut probe.input, Box::new(io::empty()));
+      Box::new(Cursor::new(taken).chain(input))
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{decode, detect, Format};
+  use std::io::{Cursor, Read};
+
+  #[test]
+  fn test_detect() {
+    assert_eq!(detect(b"\x1f\x8b\x08\x00"), Some(Format::Gzip));
+    assert_eq!(detect(b"\x1f\x8b"), None);
+    assert_eq!(detect(b"BZh91AY&SY\x00"), Some(Format::Bzip2));
+    assert_eq!(detect(b"BZh9\x17rE8P\x90\x00"), Some(Format::Bzip2));
+    assert_eq!(detect(b"BZhello world"), None);
+    assert_eq!(detect(b"BZh0"), None);
+  }
+
+  #[test]
+  fn test_decode_falls_back() {
+    // gzip のマジックナンバーの後が壊れたヘッダでも、元のバイトをそのまま返す
+    let bytes = b"\x1f\x8b\x08\xffnot really gzip\n".repeat(1000);
+    let mut decoded = decode(Format::Gzip, Box::new(Cursor::new(bytes.clone())));
+    let mut out = Vec::new();
+    decoded.read_to_end(&mut out).unwrap();
+    assert_eq!(out, bytes);
+  }
+}
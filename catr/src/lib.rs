@@ -1,39 +1,317 @@
-use clap::Parser;
+use clap::{Parser, ValueEnum};
+use decompress::detect;
 use std::error::Error;
 use std::fs::File;
-use std::io::{self, BufRead, BufReader};
+use std::io::{self, BufRead, BufReader, BufWriter, StdinLock, StdoutLock, Write};
+
+mod decompress;
 
 type MyResult<T> = Result<T, Box<dyn Error>>;
 
-fn open(filename: &str) -> MyResult<Box<dyn BufRead>> {
-  match filename {
-    "-" => Ok(Box::new(BufReader::new(io::stdin()))),
-    _ => Ok(Box::new(BufReader::new(File::open(filename)?))),
+const BUF_SIZE: usize = 128 * 1024;
+
+/// An opened input. Uncompressed files and STDIN keep their own types so
+/// that `io::copy` can use the zero-copy system calls for them.
+enum Input {
+  File(BufReader<File>),
+  Stdin(StdinLock<'static>),
+  Decoded(Box<dyn BufRead>),
+}
+
+/// Why an input could not be copied to the output. An input that cannot
+/// be read, such as a corrupt compressed file, fails on its own, while a
+/// failed write ends the run.
+enum CatError {
+  Read(io::Error),
+  Write(io::Error),
+}
+
+impl Input {
+  /// Copy the rest of the input to `out` unchanged. On Linux `io::copy`
+  /// uses copy_file_range / sendfile / splice when it can, and otherwise
+  /// reads and writes through the buffer of `out`.
+  fn copy_to(self, out: &mut BufWriter<StdoutLock>) -> Result<(), CatError> {
+    // io::copy からはどちら側で失敗したのか分からないので、残りを copy_tagged で
+    // やり直す。同じ失敗がもう一度起きて、読み書きのどちらなのかが分かる
+    match self {
+      Input::File(mut file) => match io::copy(&mut file, out) {
+        Ok(_) => Ok(()),
+        Err(_) => copy_tagged(&mut file, out),
+      },
+      Input::Stdin(mut stdin) => match io::copy(&mut stdin, out) {
+        Ok(_) => Ok(()),
+        Err(_) => copy_tagged(&mut stdin, out),
+      },
+      Input::Decoded(mut reader) => copy_tagged(&mut reader, out),
+    }
+  }
+
+  fn into_reader(self) -> Box<dyn BufRead> {
+    match self {
+      Input::File(file) => Box::new(file),
+      Input::Stdin(stdin) => Box::new(stdin),
+      Input::Decoded(reader) => reader,
+    }
+  }
+}
+
+/// Copy the rest of `reader` to `out`, telling read errors from write errors
+fn copy_tagged(reader: &mut impl BufRead, out: &mut impl Write) -> Result<(), CatError> {
+  loop {
+    let buf = reader.fill_buf().map_err(CatError::Read)?;
+    if buf.is_empty() {
+      return Ok(());
+    }
+    out.write_all(buf).map_err(CatError::Write)?;
+    let len = buf.len();
+    reader.consume(len);
+  }
+}
+
+/// Open `filename`, decompressing it on the fly when `decompress` is auto
+/// and its first bytes are the magic bytes of gzip, zstd, xz or bzip2.
+/// An input that only looks compressed is read as it is.
+fn open(filename: &str, decompress: Decompress) -> MyResult<Input> {
+  let mut input = match filename {
+    "-" => Input::Stdin(io::stdin().lock()),
+    _ => Input::File(BufReader::with_capacity(BUF_SIZE, File::open(filename)?)),
+  };
+  if decompress == Decompress::Auto {
+    // 読み込んだ先頭はバッファに残るので、圧縮されていなければそのまま使える
+    let format = match &mut input {
+      Input::File(file) => detect(file.fill_buf()?),
+      Input::Stdin(stdin) => detect(stdin.fill_buf()?),
+      Input::Decoded(_) => None,
+    };
+    if let Some(format) = format {
+      input = Input::Decoded(decompress::decode(format, input.into_reader()));
+    }
   }
+  Ok(input)
 }
 
 pub fn run(args: Args) -> MyResult<()> {
-  for filename in args.files {
-    match open(&filename) {
-      Err(e) => eprintln!("Failed to open {}: {}", filename, e),
-      Ok(file) => {
-        let mut non_blank_line_number = 1;
-        for (line_number, line) in file.lines().enumerate() {
-          let line = line?;
-          if args.number_lines {
-            print!("{:6}\t", line_number + 1);
-          } else if args.number_non_blank_lines && !line.trim().is_empty() {
-            print!("{:6}\t", non_blank_line_number);
-            non_blank_line_number += 1;
+  let show = Show {
+    ends: args.show_ends || args.show_all || args.show_nonprinting_ends,
+    tabs: args.show_tabs || args.show_all || args.show_nonprinting_tabs,
+    nonprinting: args.show_nonprinting
+      || args.show_all
+      || args.show_nonprinting_ends
+      || args.show_nonprinting_tabs,
+  };
+  let plain = !(args.number_lines
+    || args.number_non_blank_lines
+    || args.squeeze_blank
+    || show.ends
+    || show.tabs
+    || show.nonprinting);
+  let format = args.number_format.clone().unwrap_or_default();
+  let mut out = BufWriter::with_capacity(BUF_SIZE, io::stdout().lock());
+  let mut state = LineState::new(format.start);
+  let mut failed = 0;
+
+  for filename in &args.files {
+    match open(filename, args.decompress) {
+      Err(e) => {
+        eprintln!("Failed to open {}: {}", filename, e);
+        failed += 1;
+      }
+      Ok(input) => {
+        let copied = if plain {
+          // 加工しないなら行に分けずにそのまま流す
+          input.copy_to(&mut out)
+        } else {
+          if args.number_per_file {
+            state = LineState::new(format.start);
           }
-          println!("{}", line);
+          cat_lines(
+            input.into_reader(),
+            &mut out,
+            &args,
+            &show,
+            &format,
+            &mut state,
+          )
+        };
+        match copied {
+          Ok(()) => {}
+          Err(CatError::Read(e)) => {
+            eprintln!("Failed to read {}: {}", filename, e);
+            failed += 1;
+          }
+          Err(CatError::Write(e)) => return Err(e.into()),
         }
       }
     }
   }
+  out.flush()?;
+
+  if failed > 0 {
+    return Err(
+      format!(
+        "{} of {} inputs could not be read",
+        failed,
+        args.files.len()
+      )
+      .into(),
+    );
+  }
+  Ok(())
+}
+
+fn cat_lines(
+  mut file: impl BufRead,
+  out: &mut impl Write,
+  args: &Args,
+  show: &Show,
+  format: &NumberFormat,
+  state: &mut LineState,
+) -> Result<(), CatError> {
+  let mut line = Vec::new();
+  loop {
+    line.clear();
+    if file.read_until(b'\n', &mut line).map_err(CatError::Read)? == 0 {
+      return Ok(());
+    }
+    // 前のファイルが改行で終わっていなければ、その行の続きになる
+    let continued = state.mid_line;
+    state.mid_line = !line.ends_with(b"\n");
+    let blank = !continued && line == b"\n";
+    if args.squeeze_blank && blank && state.prev_blank {
+      continue;
+    }
+    state.prev_blank = blank;
+
+    if !continued && (args.number_lines || (args.number_non_blank_lines && !blank)) {
+      write!(
+        out,
+        "{:width$}{}",
+        state.number,
+        format.separator,
+        width = format.width
+      )
+      .map_err(CatError::Write)?;
+      state.number += 1;
+    }
+    write_line(out, &line, show).map_err(CatError::Write)?;
+  }
+}
+
+/// Numbering and blank line state, carried over from one input to the next
+/// unless numbering restarts for each file
+struct LineState {
+  number: u64,
+  prev_blank: bool,
+  /// The last line written did not end with a newline
+  mid_line: bool,
+}
+
+impl LineState {
+  fn new(start: u64) -> Self {
+    LineState {
+      number: start,
+      prev_blank: false,
+      mid_line: false,
+    }
+  }
+}
+
+/// How line numbers are written for -n and -b
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NumberFormat {
+  width: usize,
+  separator: String,
+  start: u64,
+}
+
+impl Default for NumberFormat {
+  fn default() -> Self {
+    NumberFormat {
+      width: 6,
+      separator: "\t".to_string(),
+      start: 1,
+    }
+  }
+}
+
+/// Parse a comma separated list of `width=N`, `sep=STRING` and `start=N`.
+/// A `\t` in the separator stands for a tab.
+fn parse_number_format(val: &str) -> Result<NumberFormat, String> {
+  let mut format = NumberFormat::default();
+  for item in val.split(',') {
+    let (key, value) = item
+      .split_once('=')
+      .ok_or_else(|| format!("expected KEY=VALUE, found '{}'", item))?;
+    match key {
+      "width" => format.width = value.parse().map_err(|e| format!("width: {}", e))?,
+      "sep" => format.separator = value.replace("\\t", "\t"),
+      "start" => format.start = value.parse().map_err(|e| format!("start: {}", e))?,
+      _ => {
+        return Err(format!(
+          "unknown key '{}' (expected width, sep or start)",
+          key
+        ))
+      }
+    }
+  }
+  Ok(format)
+}
+
+/// How special characters are made visible
+struct Show {
+  ends: bool,
+  tabs: bool,
+  nonprinting: bool,
+}
+
+/// Write one line, including its newline if it has one
+fn write_line(out: &mut impl Write, line: &[u8], show: &Show) -> io::Result<()> {
+  let (content, newline) = match line.strip_suffix(b"\n") {
+    Some(content) => (content, true),
+    None => (line, false),
+  };
+  // GNU cat と同じく、-E では行末の CR も ^M として見せる
+  let (content, carriage_return) = match content.strip_suffix(b"\r") {
+    Some(content) if show.ends && newline => (content, true),
+    _ => (content, false),
+  };
+  if !show.tabs && !show.nonprinting {
+    out.write_all(content)?;
+  } else {
+    for &byte in content {
+      match byte {
+        b'\t' if !show.tabs => out.write_all(b"\t")?,
+        _ if show.nonprinting || byte == b'\t' => write_nonprinting(out, byte)?,
+        _ => out.write_all(&[byte])?,
+      }
+    }
+  }
+  if carriage_return {
+    out.write_all(b"^M")?;
+  }
+  if show.ends && newline {
+    out.write_all(b"$")?;
+  }
+  if newline {
+    out.write_all(b"\n")?;
+  }
   Ok(())
 }
 
+/// Write `byte` in the notation of `cat -v`: control characters as `^X`,
+/// DEL as `^?` and bytes with the high bit set as `M-` followed by the
+/// notation of the low seven bits
+fn write_nonprinting(out: &mut impl Write, byte: u8) -> io::Result<()> {
+  if byte >= 0x80 {
+    out.write_all(b"M-")?;
+  }
+  match byte & 0x7f {
+    low @ 0..=0x1f => out.write_all(&[b'^', low + 0x40]),
+    0x7f => out.write_all(b"^?"),
+    low => out.write_all(&[low]),
+  }
+}
+
 /// cat command with Rust
 #[derive(Debug, Parser)]
 #[command(version, author, about)]
@@ -47,4 +325,45 @@ pub struct Args {
   /// Number non-blank output lines
   #[arg(long = "number-nonblank", short = 'b', conflicts_with = "number_lines")]
   number_non_blank_lines: bool,
+  /// Restart line numbers at the beginning of each file
+  #[arg(long)]
+  number_per_file: bool,
+  /// Format of line numbers, e.g. "width=4,sep=: ,start=0"
+  #[arg(long, value_name = "FORMAT", value_parser = parse_number_format)]
+  number_format: Option<NumberFormat>,
+  /// Decompress gzip, zstd, xz and bzip2 inputs, detected by their contents
+  #[arg(
+    long,
+    value_name = "WHEN",
+    value_enum,
+    default_value_t = Decompress::Auto
+  )]
+  decompress: Decompress,
+  /// Equivalent to -vET
+  #[arg(long, short = 'A')]
+  show_all: bool,
+  /// Equivalent to -vE
+  #[arg(short = 'e')]
+  show_nonprinting_ends: bool,
+  /// Display $ at end of each line
+  #[arg(long, short = 'E')]
+  show_ends: bool,
+  /// Suppress repeated empty output lines
+  #[arg(long, short)]
+  squeeze_blank: bool,
+  /// Equivalent to -vT
+  #[arg(short = 't')]
+  show_nonprinting_tabs: bool,
+  /// Display TAB characters as ^I
+  #[arg(long, short = 'T')]
+  show_tabs: bool,
+  /// Use ^ and M- notation, except for LFD and TAB
+  #[arg(long, short = 'v')]
+  show_nonprinting: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Decompress {
+  Auto,
+  Never,
 }
@@ -10,6 +10,7 @@ const EMPTY: &str = "tests/inputs/empty.txt";
 const FOX: &str = "tests/inputs/fox.txt";
 const SPIDERS: &str = "tests/inputs/spiders.txt";
 const BUSTLE: &str = "tests/inputs/the-bustle.txt";
+const NONPRINTING: &str = "tests/inputs/nonprinting.txt";
 
 // --------------------------------------------------
 #[test]
@@ -44,38 +45,35 @@ fn skips_bad_file() -> Result<()> {
   let bad = gen_bad_file();
   let expected = format!("{bad}: .* [(]os error 2[)]");
   Command::cargo_bin(PRG)?
-    .arg(&bad)
+    .args([FOX, &bad])
     .assert()
-    .success()
+    .failure()
+    .stdout(predicate::str::contains("The quick brown fox"))
     .stderr(predicate::str::is_match(expected)?);
   Ok(())
 }
 
 // --------------------------------------------------
 fn run(args: &[&str], expected_file: &str) -> Result<()> {
-  let expected = fs::read_to_string(expected_file)?;
+  let expected = fs::read(expected_file)?;
   let output = Command::cargo_bin(PRG)?.args(args).output().unwrap();
   assert!(output.status.success());
-
-  let stdout = String::from_utf8(output.stdout).expect("invalid UTF-8");
-  assert_eq!(stdout, expected);
+  assert_eq!(output.stdout, expected);
 
   Ok(())
 }
 
 // --------------------------------------------------
 fn run_stdin(input_file: &str, args: &[&str], expected_file: &str) -> Result<()> {
-  let input = fs::read_to_string(input_file)?;
-  let expected = fs::read_to_string(expected_file)?;
+  let input = fs::read(input_file)?;
+  let expected = fs::read(expected_file)?;
   let output = Command::cargo_bin(PRG)?
     .write_stdin(input)
     .args(args)
     .output()
     .unwrap();
   assert!(output.status.success());
-
-  let stdout = String::from_utf8(output.stdout).expect("invalid UTF-8");
-  assert_eq!(stdout, expected);
+  assert_eq!(output.stdout, expected);
   Ok(())
 }
 
@@ -197,3 +195,224 @@ fn all_n() -> Result<()> {
 fn all_b() -> Result<()> {
   run(&[FOX, SPIDERS, BUSTLE, "-b"], "tests/expected/all.b.out")
 }
+
+// --------------------------------------------------
+#[test]
+fn nonprinting_show_all() -> Result<()> {
+  run(&["-A", NONPRINTING], "tests/expected/nonprinting.txt.A.out")?;
+  run(
+    &["--show-all", NONPRINTING],
+    "tests/expected/nonprinting.txt.A.out",
+  )?;
+  run(
+    &["-vET", NONPRINTING],
+    "tests/expected/nonprinting.txt.A.out",
+  )
+}
+
+// --------------------------------------------------
+#[test]
+fn nonprinting_show_ends() -> Result<()> {
+  run(&["-E", NONPRINTING], "tests/expected/nonprinting.txt.E.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn nonprinting_show_tabs() -> Result<()> {
+  run(&["-T", NONPRINTING], "tests/expected/nonprinting.txt.T.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn nonprinting_show_nonprinting() -> Result<()> {
+  run(&["-v", NONPRINTING], "tests/expected/nonprinting.txt.v.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn nonprinting_e_and_t() -> Result<()> {
+  run(&["-e", NONPRINTING], "tests/expected/nonprinting.txt.e.out")?;
+  run(&["-t", NONPRINTING], "tests/expected/nonprinting.txt.t.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn nonprinting_squeeze_blank() -> Result<()> {
+  run(&["-s", NONPRINTING], "tests/expected/nonprinting.txt.s.out")?;
+  run(
+    &["-sn", NONPRINTING],
+    "tests/expected/nonprinting.txt.sn.out",
+  )?;
+  run(
+    &["-sb", NONPRINTING],
+    "tests/expected/nonprinting.txt.sb.out",
+  )
+}
+
+// --------------------------------------------------
+#[test]
+fn nonprinting_stdin() -> Result<()> {
+  run_stdin(
+    NONPRINTING,
+    &["--show-nonprinting"],
+    "tests/expected/nonprinting.txt.v.out",
+  )
+}
+
+// --------------------------------------------------
+#[test]
+fn plain_is_byte_identical() -> Result<()> {
+  run(&[NONPRINTING], NONPRINTING)?;
+  run(&[FOX, NONPRINTING, SPIDERS], "tests/expected/plain.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn plain_stdin_between_files() -> Result<()> {
+  run_stdin(
+    FOX,
+    &[NONPRINTING, "-", NONPRINTING],
+    "tests/expected/plain.stdin.out",
+  )
+}
+
+// --------------------------------------------------
+#[test]
+fn all_n_per_file() -> Result<()> {
+  run(
+    &[FOX, SPIDERS, BUSTLE, "-n", "--number-per-file"],
+    "tests/expected/all.n.per_file.out",
+  )
+}
+
+// --------------------------------------------------
+#[test]
+fn all_b_per_file() -> Result<()> {
+  run(
+    &["--number-per-file", "-b", FOX, SPIDERS, BUSTLE],
+    "tests/expected/all.b.per_file.out",
+  )
+}
+
+// --------------------------------------------------
+#[test]
+fn numbering_continues_unfinished_line() -> Result<()> {
+  run(&["-n", NONPRINTING, FOX], "tests/expected/continued.n.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn number_format() -> Result<()> {
+  Command::cargo_bin(PRG)?
+    .args(["-n", "--number-format", "width=3,sep=: ,start=0", SPIDERS])
+    .assert()
+    .success()
+    .stdout("  0: Don't worry, spiders,\n  1: I keep house\n  2: casually.\n");
+  Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_number_format() -> Result<()> {
+  Command::cargo_bin(PRG)?
+    .args(["-n", "--number-format", "width=wide", SPIDERS])
+    .assert()
+    .failure()
+    .stderr(predicate::str::contains("invalid digit found in string"));
+  Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn bustle_compressed() -> Result<()> {
+  for ext in ["gz", "zst", "xz", "bz2"] {
+    let compressed = format!("{BUSTLE}.{ext}");
+    run(&[&compressed], "tests/expected/the-bustle.txt.out")?;
+    run(&["-n", &compressed], "tests/expected/the-bustle.txt.n.out")?;
+  }
+  Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn bustle_compressed_stdin() -> Result<()> {
+  run_stdin(
+    "tests/inputs/the-bustle.txt.xz",
+    &["-b", "-"],
+    "tests/expected/the-bustle.txt.b.stdin.out",
+  )
+}
+
+// --------------------------------------------------
+#[test]
+fn compressed_between_plain() -> Result<()> {
+  run(
+    &[FOX, "tests/inputs/the-bustle.txt.gz", SPIDERS],
+    "tests/expected/all.compressed.out",
+  )
+}
+
+// --------------------------------------------------
+#[test]
+fn decompress_never() -> Result<()> {
+  let compressed = "tests/inputs/the-bustle.txt.gz";
+  run(&["--decompress=never", compressed], compressed)
+}
+
+// --------------------------------------------------
+#[test]
+fn looks_compressed_but_plain() -> Result<()> {
+  Command::cargo_bin(PRG)?
+    .args([
+      "tests/inputs/bzh.txt",
+      "tests/inputs/bzh-signature.txt",
+      FOX,
+    ])
+    .assert()
+    .success()
+    .stdout(
+      "BZhello world\nBZh91AY&SY but not bzip2 at all\n\
+      The quick brown fox jumps over the lazy dog.\n",
+    );
+  Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn truncated_compressed_fails_alone() -> Result<()> {
+  for flag in ["--decompress=auto", "-n"] {
+    Command::cargo_bin(PRG)?
+      .args([flag, "tests/inputs/truncated.gz", FOX])
+      .assert()
+      .failure()
+      .stdout(predicate::str::ends_with(
+        "The quick brown fox jumps over the lazy dog.\n",
+      ))
+      .stderr(
+        "Failed to read tests/inputs/truncated.gz: incomplete deflate stream\n\
+        Error: 1 of 2 inputs could not be read\n",
+      );
+  }
+  Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn unreadable_plain_fails_alone() -> Result<()> {
+  // -n で行ごとに読むときも、そのまま流すときも同じように失敗する
+  for flags in [&["--decompress=never"][..], &["--decompress=never", "-n"]] {
+    Command::cargo_bin(PRG)?
+      .args(flags)
+      .args(["tests", FOX])
+      .assert()
+      .failure()
+      .stdout(predicate::str::ends_with(
+        "The quick brown fox jumps over the lazy dog.\n",
+      ))
+      .stderr(
+        "Failed to read tests: Is a directory (os error 21)\n\
+        Error: 1 of 2 inputs could not be read\n",
+      );
+  }
+  Ok(())
+}
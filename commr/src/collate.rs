@@ -0,0 +1,231 @@
+use std::{
+    borrow::Cow,
+    cmp::Ordering::{self, Equal, Greater, Less},
+};
+
+/// How lines are ordered, mirroring the `sort` options that produced them.
+/// All of them are locale independent.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Collation {
+    pub insensitive: bool,
+    pub numeric: bool,
+    pub version: bool,
+    pub ignore_leading_blanks: bool,
+    /// Compare only this field (1-based) instead of the whole line
+    pub field: Option<usize>,
+    /// Field separator, runs of blanks when not given
+    pub separator: Option<char>,
+}
+
+impl Collation {
+    pub fn compare(&self, a: &str, b: &str) -> Ordering {
+        let (a, b) = (self.key(a), self.key(b));
+        let order = if self.numeric {
+            numeric_cmp(&a, &b)
+        } else if self.version {
+            version_cmp(&a, &b)
+        } else {
+            Equal
+        };
+        // sort と同じように、同じ値の行は最後に文字列として比べる
+        order.then_with(|| a.cmp(&b))
+    }
+
+    fn key<'a>(&self, line: &'a str) -> Cow<'a, str> {
+        let line = match self.field {
+            Some(field) => select_field(line, field, self.separator),
+            None => line,
+        };
+        let line = if self.ignore_leading_blanks {
+            line.trim_start_matches([' ', '\t'])
+        } else {
+            line
+        };
+        if self.insensitive {
+            Cow::Owned(caseless::default_case_fold_str(line))
+        } else {
+            Cow::Borrowed(line)
+        }
+    }
+}
+
+/// The `field`th (1-based) field of `line`, or an empty string if it has fewer fields
+fn select_field(line: &str, field: usize, separator: Option<char>) -> &str {
+    match separator {
+        Some(separator) => line.split(separator).nth(field - 1),
+        None => line
+            .split([' ', '\t'])
+            .filter(|f| !f.is_empty())
+            .nth(field - 1),
+    }
+    .unwrap_or("")
+}
+
+/// Compare the leading numbers of two lines like `sort -n`.
+/// A line without a leading number counts as zero.
+fn numeric_cmp(a: &str, b: &str) -> Ordering {
+    let (a_neg, a_int, a_frac) = split_number(a);
+    let (b_neg, b_int, b_frac) = split_number(b);
+    let a_zero = a_int.is_empty() && a_frac.is_empty();
+    let b_zero = b_int.is_empty() && b_frac.is_empty();
+    let a_neg = a_neg && !a_zero;
+    let b_neg = b_neg && !b_zero;
+
+    let magnitude = a_int
+        .len()
+        .cmp(&b_int.len())
+        .then_with(|| a_int.cmp(b_int))
+        .then_with(|| a_frac.cmp(b_frac));
+    match (a_neg, b_neg) {
+        (false, false) => magnitude,
+        (true, true) => magnitude.reverse(),
+        (true, false) => Less,
+        (false, true) => Greater,
+    }
+}
+
+/// Split a leading number into its sign, integer digits without leading
+/// zeros and fraction digits without trailing zeros
+fn split_number(line: &str) -> (bool, &str, &str) {
+    let line = line.trim_start_matches([' ', '\t']);
+    let (negative, line) = match line.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, line),
+    };
+    let int_len = line.bytes().take_while(u8::is_ascii_digit).count();
+    let (int, rest) = line.split_at(int_len);
+    let frac = match rest.strip_prefix('.') {
+        Some(rest) => {
+            let frac_len = rest.bytes().take_while(u8::is_ascii_digit).count();
+            &rest[..frac_len]
+        }
+        None => "",
+    };
+    (
+        negative,
+        int.trim_start_matches('0'),
+        frac.trim_end_matches('0'),
+    )
+}
+
+/// Compare two lines as version strings like `sort -V`: runs of digits are
+/// compared as numbers, letters sort before other characters and `~` sorts
+/// before everything, even the end of the string.
+fn version_cmp(a: &str, b: &str) -> Ordering {
+    let (mut a, mut b) = (a.as_bytes(), b.as_bytes());
+    while !a.is_empty() || !b.is_empty() {
+        let a_len = a.iter().take_while(|c| !c.is_ascii_digit()).count();
+        let b_len = b.iter().take_while(|c| !c.is_ascii_digit()).count();
+        let order = text_cmp(&a[..a_len], &b[..b_len]);
+        if order != Equal {
+            return order;
+        }
+        (a, b) = (&a[a_len..], &b[b_len..]);
+
+        let a_len = a.iter().take_while(|c| c.is_ascii_digit()).count();
+        let b_len = b.iter().take_while(|c| c.is_ascii_digit()).count();
+        let order = digits_cmp(&a[..a_len], &b[..b_len]);
+        if order != Equal {
+            return order;
+        }
+        (a, b) = (&a[a_len..], &b[b_len..]);
+    }
+    Equal
+}
+
+fn text_cmp(a: &[u8], b: &[u8]) -> Ordering {
+    let weight = |c: Option<&u8>| match c {
+        None => 0,
+        Some(b'~') => -1,
+        Some(c) if c.is_ascii_alphabetic() => i32::from(*c),
+        Some(c) => i32::from(*c) + 256,
+    };
+    for i in 0..a.len().max(b.len()) {
+        let order = weight(a.get(i)).cmp(&weight(b.get(i)));
+        if order != Equal {
+            return order;
+        }
+    }
+    Equal
+}
+
+fn digits_cmp(a: &[u8], b: &[u8]) -> Ordering {
+    let a = &a[a.iter().take_while(|c| **c == b'0').count()..];
+    let b = &b[b.iter().take_while(|c| **c == b'0').count()..];
+    a.len().cmp(&b.len()).then_with(|| a.cmp(b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{numeric_cmp, select_field, version_cmp, Collation};
+    use std::cmp::Ordering::{Equal, Greater, Less};
+
+    #[test]
+    fn test_numeric_cmp() {
+        assert_eq!(numeric_cmp("2", "10"), Less);
+        assert_eq!(numeric_cmp("10", "9"), Greater);
+        assert_eq!(numeric_cmp("007", "7"), Equal);
+        assert_eq!(numeric_cmp("1.50", "1.5"), Equal);
+        assert_eq!(numeric_cmp("1.05", "1.5"), Less);
+        assert_eq!(numeric_cmp("-3", "-20"), Greater);
+        assert_eq!(numeric_cmp("-1", "0"), Less);
+        assert_eq!(numeric_cmp("-0", "0"), Equal);
+        assert_eq!(numeric_cmp("abc", "0"), Equal);
+        assert_eq!(numeric_cmp("  12 apples", "3 pears"), Greater);
+    }
+
+    #[test]
+    fn test_version_cmp() {
+        assert_eq!(version_cmp("1.2", "1.10"), Less);
+        assert_eq!(version_cmp("1.10", "1.9"), Greater);
+        assert_eq!(version_cmp("file-2.txt", "file-10.txt"), Less);
+        assert_eq!(version_cmp("1.0~rc1", "1.0"), Less);
+        assert_eq!(version_cmp("1.0a", "1.0+"), Less);
+        assert_eq!(version_cmp("1.01", "1.1"), Equal);
+    }
+
+    #[test]
+    fn test_select_field() {
+        assert_eq!(select_field("1,apple,red", 2, Some(',')), "apple");
+        assert_eq!(select_field("1,,red", 2, Some(',')), "");
+        assert_eq!(select_field("1,apple", 3, Some(',')), "");
+        assert_eq!(select_field("  1  apple\tred", 2, None), "apple");
+        assert_eq!(select_field("  1  apple\tred", 3, None), "red");
+    }
+
+    #[test]
+    fn test_collation() {
+        let plain = Collation::default();
+        assert_eq!(plain.compare("B", "a"), Less);
+        assert_eq!(plain.compare(" b", "a"), Less);
+
+        let insensitive = Collation {
+            insensitive: true,
+            ..Default::default()
+        };
+        assert_eq!(insensitive.compare("B", "a"), Greater);
+        assert_eq!(insensitive.compare("STRASSE", "straße"), Equal);
+
+        let blanks = Collation {
+            ignore_leading_blanks: true,
+            ..Default::default()
+        };
+        assert_eq!(blanks.compare("  b", "a"), Greater);
+
+        // Numbers that compare equal fall back to comparing the whole line
+        let numeric = Collation {
+            numeric: true,
+            ..Default::default()
+        };
+        assert_eq!(numeric.compare("10", "9"), Greater);
+        assert_eq!(numeric.compare("01", "1"), Less);
+
+        let by_field = Collation {
+            field: Some(1),
+            separator: Some(','),
+            ..Default::default()
+        };
+        assert_eq!(by_field.compare("2,old", "2,new"), Equal);
+        assert_eq!(by_field.compare("1,z", "2,a"), Less);
+    }
+}
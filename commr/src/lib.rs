@@ -1,12 +1,16 @@
 use crate::Column::*;
 use anyhow::{anyhow, Result};
-use clap::Parser;
+use clap::{Parser, ValueEnum};
+use collate::Collation;
+use serde_json::json;
 use std::{
     cmp::Ordering::{Equal, Greater, Less},
     fs::File,
     io::{self, BufRead, BufReader},
 };
 
+mod collate;
+
 #[derive(Debug, Parser)]
 #[command(author, version, about)]
 /// comm clone written in Rust
@@ -35,17 +39,143 @@ pub struct Config {
     #[arg(short, long)]
     insensitive: bool,
 
+    /// Compare lines by their leading numbers, as sorted by `sort -n`
+    #[arg(long, visible_alias = "numeric-sort", conflicts_with = "version_sort")]
+    numeric: bool,
+
+    /// Compare lines as version numbers, as sorted by `sort -V`
+    #[arg(long)]
+    version_sort: bool,
+
+    /// Ignore leading blanks when comparing lines
+    #[arg(short = 'b', long)]
+    ignore_leading_blanks: bool,
+
+    /// Compare lines by this field (1-based) instead of the whole line
+    #[arg(
+        short,
+        long,
+        value_name = "FIELD",
+        value_parser = clap::value_parser!(u64).range(1..),
+    )]
+    key: Option<u64>,
+
+    /// Field separator for --key (default: runs of blanks)
+    #[arg(short = 't', long, value_name = "CHAR", requires = "key")]
+    field_separator: Option<char>,
+
+    /// For lines common to both files, print the line from FILE2 as well
+    #[arg(long)]
+    show_both: bool,
+
+    /// Replace invalid UTF-8 with U+FFFD instead of failing
+    #[arg(long)]
+    lossy: bool,
+
+    /// Print the number of lines in each column at the end
+    #[arg(long)]
+    total: bool,
+
+    /// Line delimiter is NUL, not newline
+    #[arg(short, long)]
+    zero_terminated: bool,
+
+    /// Output format: one record per line with its column number for json
+    /// and csv; csv totals are written as the rows total1, total2, total3
+    #[arg(long, value_enum, default_value_t = Format::Text)]
+    format: Format,
+
     /// Output delimiter
     #[arg(short, long, default_value = "\t")]
     delimiter: String,
+
+    /// Fail as soon as an input is found not to be in sorted order
+    #[arg(long, conflicts_with = "nocheck_order")]
+    check_order: bool,
+
+    /// Do not check that the inputs are in sorted order
+    #[arg(long)]
+    nocheck_order: bool,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Format {
+    Text,
+    Json,
+    Csv,
+}
+
+#[derive(Debug, Clone, Copy)]
 enum Column {
     One,
     Two,
     Three,
 }
 
+impl Column {
+    fn index(self) -> usize {
+        match self {
+            One => 0,
+            Two => 1,
+            Three => 2,
+        }
+    }
+}
+
+/// Sort order checking in the same manner as GNU comm: by default a disorder
+/// is only reported once an unpairable line has been seen.
+struct OrderCheck {
+    collation: Collation,
+    fatal: bool,
+    disabled: bool,
+    seen_unpairable: bool,
+    line_nums: [usize; 2],
+    unsorted: [bool; 2],
+}
+
+impl OrderCheck {
+    fn new(config: &Config, collation: Collation) -> Self {
+        OrderCheck {
+            collation,
+            fatal: config.check_order,
+            disabled: config.nocheck_order,
+            seen_unpairable: false,
+            line_nums: [0; 2],
+            unsorted: [false; 2],
+        }
+    }
+
+    /// Check that `next` does not sort before `prev` in file `file` (0 or 1)
+    fn check(&mut self, file: usize, prev: Option<&str>, next: Option<&str>) -> Result<()> {
+        if next.is_none() {
+            return Ok(());
+        }
+        self.line_nums[file] += 1;
+        if self.disabled || self.unsorted[file] || !(self.fatal || self.seen_unpairable) {
+            return Ok(());
+        }
+        if let (Some(prev), Some(next)) = (prev, next) {
+            if self.collation.compare(prev, next) == Greater {
+                self.unsorted[file] = true;
+                let msg = format!(
+                    "file {} is not in sorted order (line {})",
+                    file + 1,
+                    self.line_nums[file]
+                );
+                if self.fatal {
+                    return Err(anyhow!(msg));
+                }
+                eprintln!("{msg}");
+            }
+        }
+        Ok(())
+    }
+
+    fn is_unsorted(&self) -> bool {
+        self.unsorted.iter().any(|unsorted| *unsorted)
+    }
+}
+
 pub fn run(config: Config) -> Result<()> {
     let file1 = &config.file1;
     let file2 = &config.file2;
@@ -54,85 +184,179 @@ pub fn run(config: Config) -> Result<()> {
         return Err(anyhow!("Both input files cannot be STDIN (\"-\")"));
     }
 
-    let case = |line: String| {
-        if config.insensitive {
-            line.to_lowercase()
+    let collation = Collation {
+        insensitive: config.insensitive,
+        numeric: config.numeric,
+        version: config.version_sort,
+        ignore_leading_blanks: config.ignore_leading_blanks,
+        field: config.key.map(|key| key as usize),
+        separator: config.field_separator,
+    };
+
+    let terminator = if config.zero_terminated { '\0' } else { '\n' };
+    let mut counts = [0; 3];
+    let mut csv_out = csv::Writer::from_writer(io::stdout());
+    if config.format == Format::Csv {
+        if config.show_both {
+            csv_out.write_record(["column", "line", "line2"])?;
         } else {
-            line
+            csv_out.write_record(["column", "line"])?;
         }
-    };
+    }
 
-    let print_column = |line: String, column: Column| match column {
-        One => {
-            if !config.suppress_unique1 {
-                println!("{}", line)
-            }
+    let mut print_column = |line: &str, line2: Option<&str>, column: Column| -> Result<()> {
+        counts[column.index()] += 1;
+        let (suppressed, indent) = match column {
+            One => (config.suppress_unique1, 0),
+            Two => (config.suppress_unique2, !config.suppress_unique1 as usize),
+            Three => (
+                config.suppress_common,
+                !config.suppress_unique1 as usize + !config.suppress_unique2 as usize,
+            ),
+        };
+        if suppressed {
+            return Ok(());
         }
-        Two => {
-            if !config.suppress_unique2 {
-                if !config.suppress_unique1 {
-                    print!("{}", config.delimiter);
-                }
-                println!("{}", line);
+        let number = column.index() + 1;
+        match (config.format, line2) {
+            (Format::Text, None) => {
+                print!("{}{}{}", config.delimiter.repeat(indent), line, terminator)
             }
-        }
-        Three => {
-            if !config.suppress_common {
-                if !config.suppress_unique1 {
-                    print!("{}", config.delimiter);
+            (Format::Text, Some(line2)) => print!(
+                "{}{}{}{}{}",
+                config.delimiter.repeat(indent),
+                line,
+                config.delimiter,
+                line2,
+                terminator
+            ),
+            (Format::Json, None) => println!("{}", json!({ "column": number, "line": line })),
+            (Format::Json, Some(line2)) => println!(
+                "{}",
+                json!({ "column": number, "line": line, "line2": line2 })
+            ),
+            (Format::Csv, line2) => {
+                let mut record = vec![number.to_string(), line.to_string()];
+                if config.show_both {
+                    record.push(line2.unwrap_or("").to_string());
                 }
-                if !config.suppress_unique2 {
-                    print!("{}", config.delimiter);
-                }
-                println!("{}", line);
+                csv_out.write_record(record)?
             }
         }
+        Ok(())
     };
 
     let mut lines1 = match open(file1) {
         Err(e) => return Err(anyhow!("{file1}: {e}")),
-        Ok(file) => file.lines().map_while(Result::ok).map(case),
+        Ok(file) => records(file, file1, terminator as u8, config.lossy),
     };
     let mut lines2 = match open(file2) {
         Err(e) => return Err(anyhow!("{file2}: {e}")),
-        Ok(file) => file.lines().map_while(Result::ok).map(case),
+        Ok(file) => records(file, file2, terminator as u8, config.lossy),
     };
 
-    let mut line1 = lines1.next();
-    let mut line2 = lines2.next();
+    let mut order = OrderCheck::new(&config, collation);
+    let mut line1 = lines1.next().transpose()?;
+    order.check(0, None, line1.as_deref())?;
+    let mut line2 = lines2.next().transpose()?;
+    order.check(1, None, line2.as_deref())?;
 
     while line1.is_some() || line2.is_some() {
         match (&line1, &line2) {
-            (Some(val1), Some(val2)) => match val1.cmp(val2) {
+            (Some(val1), Some(val2)) => match collation.compare(val1, val2) {
                 Less => {
-                    print_column(val1.to_string(), One);
-                    line1 = lines1.next();
+                    print_column(val1, None, One)?;
+                    order.seen_unpairable = true;
+                    advance(&mut lines1, &mut line1, &mut order, 0)?;
                 }
                 Greater => {
-                    print_column(val2.to_string(), Two);
-                    line2 = lines2.next();
+                    print_column(val2, None, Two)?;
+                    order.seen_unpairable = true;
+                    advance(&mut lines2, &mut line2, &mut order, 1)?;
                 }
                 Equal => {
-                    print_column(val1.to_string(), Three);
-                    line1 = lines1.next();
-                    line2 = lines2.next();
+                    print_column(val1, config.show_both.then_some(val2.as_str()), Three)?;
+                    advance(&mut lines1, &mut line1, &mut order, 0)?;
+                    advance(&mut lines2, &mut line2, &mut order, 1)?;
                 }
             },
             (Some(val1), None) => {
-                print_column(val1.to_string(), One);
-                line1 = lines1.next();
+                print_column(val1, None, One)?;
+                order.seen_unpairable = true;
+                advance(&mut lines1, &mut line1, &mut order, 0)?;
             }
             (None, Some(val2)) => {
-                print_column(val2.to_string(), Two);
-                line2 = lines2.next();
+                print_column(val2, None, Two)?;
+                order.seen_unpairable = true;
+                advance(&mut lines2, &mut line2, &mut order, 1)?;
             }
             _ => (),
         }
     }
 
+    if config.total {
+        match config.format {
+            Format::Text => print!(
+                "{}{d}{}{d}{}{d}total{}",
+                counts[0],
+                counts[1],
+                counts[2],
+                terminator,
+                d = config.delimiter
+            ),
+            Format::Json => println!(
+                "{}",
+                json!({ "total": { "1": counts[0], "2": counts[1], "3": counts[2] } })
+            ),
+            Format::Csv => {
+                for (i, count) in counts.iter().enumerate() {
+                    csv_out.write_record([format!("total{}", i + 1), count.to_string()])?;
+                }
+            }
+        }
+    }
+    csv_out.flush()?;
+
+    if order.is_unsorted() {
+        return Err(anyhow!("input is not in sorted order"));
+    }
     Ok(())
 }
 
+/// Move `line` to the next line of `lines`, checking their order
+fn advance(
+    lines: &mut impl Iterator<Item = Result<String>>,
+    line: &mut Option<String>,
+    order: &mut OrderCheck,
+    file: usize,
+) -> Result<()> {
+    let next = lines.next().transpose()?;
+    order.check(file, line.as_deref(), next.as_deref())?;
+    *line = next;
+    Ok(())
+}
+
+/// Lines of `file` split by `delimiter`, read as bytes and decoded as UTF-8.
+/// Errors carry the filename and line number.
+fn records<'a>(
+    file: Box<dyn BufRead>,
+    filename: &'a str,
+    delimiter: u8,
+    lossy: bool,
+) -> impl Iterator<Item = Result<String>> + 'a {
+    file.split(delimiter).enumerate().map(move |(i, record)| {
+        let line_num = i + 1;
+        let record = record.map_err(|e| anyhow!("{filename}: line {line_num}: {e}"))?;
+        match String::from_utf8(record) {
+            Ok(line) => Ok(line),
+            Err(e) if lossy => Ok(String::from_utf8_lossy(e.as_bytes()).into_owned()),
+            Err(_) => Err(anyhow!(
+                "{filename}: line {line_num}: invalid UTF-8 (use --lossy to replace it)"
+            )),
+        }
+    })
+}
+
 fn open(filename: &str) -> Result<Box<dyn BufRead>> {
     match filename {
         "-" => Ok(Box::new(BufReader::new(io::stdin()))),
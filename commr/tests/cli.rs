@@ -342,3 +342,264 @@ fn file1_file2_123_delim() -> Result<()> {
 fn blank_file1() -> Result<()> {
     run(&[BLANK, FILE1], "tests/expected/blank_file1.out")
 }
+
+// --------------------------------------------------
+// sort order
+// --------------------------------------------------
+#[test]
+fn unsorted_cities() -> Result<()> {
+    let expected = fs::read_to_string("tests/expected/cities1_cities2.out")?;
+    Command::cargo_bin(PRG)?
+        .args(["tests/inputs/cities1.txt", "tests/inputs/cities2.txt"])
+        .assert()
+        .failure()
+        .stdout(expected)
+        .stderr(predicate::str::contains(
+            "file 1 is not in sorted order (line 2)",
+        ))
+        .stderr(predicate::str::contains(
+            "file 2 is not in sorted order (line 2)",
+        ))
+        .stderr(predicate::str::contains("input is not in sorted order"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn unsorted_cities_nocheck_order() -> Result<()> {
+    let expected = fs::read_to_string("tests/expected/cities1_cities2.out")?;
+    Command::cargo_bin(PRG)?
+        .args([
+            "--nocheck-order",
+            "tests/inputs/cities1.txt",
+            "tests/inputs/cities2.txt",
+        ])
+        .assert()
+        .success()
+        .stdout(expected)
+        .stderr("");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn unsorted_pairable_check_order() -> Result<()> {
+    const CITIES1: &str = "tests/inputs/cities1.txt";
+    Command::cargo_bin(PRG)?
+        .args([CITIES1, CITIES1])
+        .assert()
+        .success();
+    Command::cargo_bin(PRG)?
+        .args(["--check-order", CITIES1, CITIES1])
+        .assert()
+        .failure()
+        .stderr("file 1 is not in sorted order (line 2)\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn check_order_insensitive() -> Result<()> {
+    const MIXED: &str = "tests/inputs/mixed.txt";
+    Command::cargo_bin(PRG)?
+        .args(["--check-order", FILE1, MIXED])
+        .assert()
+        .failure()
+        .stderr("file 2 is not in sorted order (line 2)\n");
+    Command::cargo_bin(PRG)?
+        .args(["--check-order", "-i", FILE1, MIXED])
+        .assert()
+        .success();
+    Ok(())
+}
+
+// --------------------------------------------------
+// collation
+// --------------------------------------------------
+#[test]
+fn nums1_nums2_numeric() -> Result<()> {
+    run(
+        &[
+            "--numeric",
+            "tests/inputs/nums1.txt",
+            "tests/inputs/nums2.txt",
+        ],
+        "tests/expected/nums1_nums2.n.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn versions1_versions2_version_sort() -> Result<()> {
+    run(
+        &[
+            "--version-sort",
+            "tests/inputs/versions1.txt",
+            "tests/inputs/versions2.txt",
+        ],
+        "tests/expected/versions1_versions2.V.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn blanks_file1_ignore_leading_blanks() -> Result<()> {
+    run(
+        &["-b", "tests/inputs/blanks.txt", FILE1],
+        "tests/expected/blanks_file1.b.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_numeric_and_version_sort() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["--numeric", "--version-sort", FILE1, FILE2])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+    Ok(())
+}
+
+// --------------------------------------------------
+// totals and output formats
+// --------------------------------------------------
+#[test]
+fn file1_file2_total() -> Result<()> {
+    run(
+        &["--total", FILE1, FILE2],
+        "tests/expected/file1_file2.total.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn file1_file2_12_total_json() -> Result<()> {
+    run(
+        &["--total", "-12", "--format", "json", FILE1, FILE2],
+        "tests/expected/file1_file2.12.total.json.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn file1_file2_csv() -> Result<()> {
+    run(
+        &["--format", "csv", FILE1, FILE2],
+        "tests/expected/file1_file2.csv.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn zero1_zero2_zero_terminated_total() -> Result<()> {
+    run(
+        &[
+            "-z",
+            "--total",
+            "tests/inputs/zero1.txt",
+            "tests/inputs/zero2.txt",
+        ],
+        "tests/expected/zero1_zero2.z.total.out",
+    )
+}
+
+// --------------------------------------------------
+// key fields
+// --------------------------------------------------
+#[test]
+fn ids1_ids2_key() -> Result<()> {
+    run(
+        &[
+            "-k",
+            "1",
+            "-t",
+            ",",
+            "tests/inputs/ids1.csv",
+            "tests/inputs/ids2.csv",
+        ],
+        "tests/expected/ids1_ids2.k1.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn ids1_ids2_key_show_both() -> Result<()> {
+    run(
+        &[
+            "--key",
+            "1",
+            "--field-separator",
+            ",",
+            "--show-both",
+            "tests/inputs/ids1.csv",
+            "tests/inputs/ids2.csv",
+        ],
+        "tests/expected/ids1_ids2.k1.both.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn ids1_ids2_key_show_both_json() -> Result<()> {
+    run(
+        &[
+            "-k1",
+            "-t,",
+            "--show-both",
+            "--format",
+            "json",
+            "tests/inputs/ids1.csv",
+            "tests/inputs/ids2.csv",
+        ],
+        "tests/expected/ids1_ids2.k1.both.json.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_key() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["-k", "0", FILE1, FILE2])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("invalid value '0'"));
+    Ok(())
+}
+
+// --------------------------------------------------
+// read errors
+// --------------------------------------------------
+#[test]
+fn dies_invalid_utf8() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["tests/inputs/invalid.txt", FILE1])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "tests/inputs/invalid.txt: line 3: invalid UTF-8",
+        ));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn invalid_file1_lossy() -> Result<()> {
+    run(
+        &["--lossy", "tests/inputs/invalid.txt", FILE1],
+        "tests/expected/invalid_file1.lossy.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_read_error() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args([FILE1, "tests/inputs"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::is_match(
+            "tests/inputs: line 1: .* [(]os error 21[)]",
+        )?);
+    Ok(())
+}
@@ -7,3 +7,7 @@ echo "Hello there" > $OUTDIR/hello1.txt
 echo "Hello"  "there" > $OUTDIR/hello2.txt
 echo -n "Hello  there" > $OUTDIR/hello1.n.txt
 echo -n "Hello"  "there" > $OUTDIR/hello2.n.txt
+/usr/bin/echo -e "Hello\tthere\n" > $OUTDIR/escapes.e.txt
+/usr/bin/echo -E "Hello\tthere\n" > $OUTDIR/escapes.E.txt
+/usr/bin/echo -e "\0101\x42é\\\\" "stop\c" "ignored" > $OUTDIR/escapes.c.txt
+POSIXLY_CORRECT=1 /usr/bin/echo -e "Hello\tthere" -n > $OUTDIR/posix.txt
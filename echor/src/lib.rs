@@ -0,0 +1,217 @@
+use clap::Parser;
+use std::env;
+use std::error::Error;
+use std::ffi::{OsStr, OsString};
+use std::io::{self, Write};
+
+type MyResult<T> = Result<T, Box<dyn Error>>;
+
+/// Rust echo
+#[derive(Debug, Parser)]
+#[command(author, version, about)]
+pub struct Config {
+  /// Input text to echo
+  #[arg(value_name = "TEXT")]
+  text: Vec<OsString>,
+  /// Do not print newline
+  #[arg(short = 'n', long = "no-newline")]
+  no_newline: bool,
+  /// Enable interpretation of backslash escapes
+  #[arg(short = 'e', overrides_with = "no_escapes")]
+  escapes: bool,
+  /// Disable interpretation of backslash escapes (default)
+  #[arg(short = 'E', overrides_with = "escapes")]
+  no_escapes: bool,
+}
+
+impl Config {
+  /// Read the command line. With POSIXLY_CORRECT set, arguments are not
+  /// parsed as options and escapes are always interpreted; as in coreutils,
+  /// options are still read when the first argument is `-n`.
+  pub fn from_env() -> Self {
+    let args: Vec<OsString> = env::args_os().collect();
+    if env::var_os("POSIXLY_CORRECT").is_none() {
+      return Config::parse_from(separate_options(args));
+    }
+    if args.get(1).is_some_and(|arg| arg == "-n") {
+      let mut config = Config::parse_from(separate_options(args));
+      config.escapes = !config.no_escapes;
+      return config;
+    }
+    Config {
+      text: args.into_iter().skip(1).collect(),
+      no_newline: false,
+      escapes: true,
+      no_escapes: false,
+    }
+  }
+}
+
+/// Put a `--` after the leading options of echor, so that everything from
+/// the first other argument on is printed as it is, even `-x` or a later
+/// `-n`. Like coreutils echo, a `--` given by the user is text too.
+fn separate_options(args: Vec<OsString>) -> Vec<OsString> {
+  let mut args = args.into_iter().peekable();
+  let mut options: Vec<OsString> = args.next().into_iter().collect();
+  while let Some(arg) = args.next_if(|arg| is_option(arg)) {
+    options.push(arg);
+  }
+  options.push("--".into());
+  options.extend(args);
+  options
+}
+
+fn is_option(arg: &OsStr) -> bool {
+  match arg.to_str() {
+    Some("--no-newline" | "--help" | "--version") => true,
+    Some(arg) => {
+      arg.len() > 1 && arg.starts_with('-') && arg[1..].chars().all(|c| "neEhV".contains(c))
+    }
+    None => false,
+  }
+}
+
+pub fn run(config: Config) -> MyResult<()> {
+  let mut output = Vec::new();
+  let mut newline = !config.no_newline;
+  for (i, word) in config.text.iter().enumerate() {
+    if i > 0 {
+      output.push(b' ');
+    }
+    let bytes = word.as_encoded_bytes();
+    if config.escapes {
+      if !unescape(bytes, &mut output) {
+        // \c 以降は改行も含めて何も出さない
+        newline = false;
+        break;
+      }
+    } else {
+      output.extend_from_slice(bytes);
+    }
+  }
+  if newline {
+    output.push(b'\n');
+  }
+  let mut stdout = io::stdout();
+  stdout.write_all(&output)?;
+  stdout.flush()?;
+  Ok(())
+}
+
+/// Append `bytes` to `out` with its backslash escapes interpreted.
+/// Returns false when `\c` asks for no further output.
+fn unescape(bytes: &[u8], out: &mut Vec<u8>) -> bool {
+  let mut i = 0;
+  while i < bytes.len() {
+    if bytes[i] != b'\\' || i + 1 == bytes.len() {
+      out.push(bytes[i]);
+      i += 1;
+      continue;
+    }
+    i += 2;
+    match bytes[i - 1] {
+      b'\\' => out.push(b'\\'),
+      b'a' => out.push(0x07),
+      b'b' => out.push(0x08),
+      b'c' => return false,
+      b'e' => out.push(0x1b),
+      b'f' => out.push(0x0c),
+      b'n' => out.push(b'\n'),
+      b'r' => out.push(b'\r'),
+      b't' => out.push(b'\t'),
+      b'v' => out.push(0x0b),
+      b'0' => {
+        let (value, len) = parse_digits(&bytes[i..], 8, 3);
+        out.push(value as u8);
+        i += len;
+      }
+      b'x' => match parse_digits(&bytes[i..], 16, 2) {
+        (_, 0) => out.extend_from_slice(b"\\x"),
+        (value, len) => {
+          out.push(value as u8);
+          i += len;
+        }
+      },
+      b'u' => match parse_digits(&bytes[i..], 16, 4) {
+        (value, 4) if char::from_u32(value).is_some() => {
+          let mut buf = [0; 4];
+          let c = char::from_u32(value).unwrap_or_default();
+          out.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+          i += 4;
+        }
+        _ => out.extend_from_slice(b"\\u"),
+      },
+      other => out.extend_from_slice(&[b'\\', other]),
+    }
+  }
+  true
+}
+
+/// Value and length of up to `max` leading digits of `bytes` in `radix`
+fn parse_digits(bytes: &[u8], radix: u32, max: usize) -> (u32, usize) {
+  let mut value = 0;
+  let mut len = 0;
+  while let Some(digit) = bytes.get(len).and_then(|&b| (b as char).to_digit(radix)) {
+    if len == max {
+      break;
+    }
+    value = value * radix + digit;
+    len += 1;
+  }
+  (value, len)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{separate_options, unescape};
+  use std::ffi::OsString;
+
+  fn unescaped(text: &str) -> (Vec<u8>, bool) {
+    let mut out = Vec::new();
+    let more = unescape(text.as_bytes(), &mut out);
+    (out, more)
+  }
+
+  #[test]
+  fn test_unescape() {
+    assert_eq!(unescaped("a\\tb\\n"), (b"a\tb\n".to_vec(), true));
+    assert_eq!(unescaped("\\\\"), (b"\\".to_vec(), true));
+    assert_eq!(unescaped("\\0101\\0"), (b"A\0".to_vec(), true));
+    assert_eq!(unescaped("\\x41\\xff"), (b"A\xff".to_vec(), true));
+    assert_eq!(unescaped("\\u00e9"), ("é".as_bytes().to_vec(), true));
+    assert_eq!(unescaped("ab\\cde"), (b"ab".to_vec(), false));
+  }
+
+  #[test]
+  fn test_separate_options() {
+    let separate = |args: &[&str]| -> Vec<OsString> {
+      separate_options(args.iter().map(OsString::from).collect())
+    };
+    assert_eq!(separate(&["echor", "a", "-n"]), ["echor", "--", "a", "-n"]);
+    assert_eq!(
+      separate(&["echor", "-n", "-e", "a", "-E"]),
+      ["echor", "-n", "-e", "--", "a", "-E"]
+    );
+    assert_eq!(
+      separate(&["echor", "-x", "-ne"]),
+      ["echor", "--", "-x", "-ne"]
+    );
+    assert_eq!(
+      separate(&["echor", "--", "-n", "--"]),
+      ["echor", "--", "--", "-n", "--"]
+    );
+    assert_eq!(
+      separate(&["echor", "-", "--no-newline"]),
+      ["echor", "--", "-", "--no-newline"]
+    );
+  }
+
+  #[test]
+  fn test_unescape_literal() {
+    // 解釈できないものはそのまま残す
+    assert_eq!(unescaped("\\q"), (b"\\q".to_vec(), true));
+    assert_eq!(unescaped("\\xg"), (b"\\xg".to_vec(), true));
+    assert_eq!(unescaped("\\u12"), (b"\\u12".to_vec(), true));
+    assert_eq!(unescaped("trail\\"), (b"trail\\".to_vec(), true));
+  }
+}
@@ -5,11 +5,8 @@ use std::fs;
 type TestResult = Result<(), Box<dyn std::error::Error>>;
 
 #[test]
-fn dies_no_args() -> TestResult {
-  Command::cargo_bin("echor")?
-    .assert()
-    .failure()
-    .stderr(predicate::str::contains("USAGE"));
+fn no_args() -> TestResult {
+  Command::cargo_bin("echor")?.assert().success().stdout("\n");
   Ok(())
 }
 
@@ -34,10 +31,96 @@ fn hello2() -> TestResult {
 
 #[test]
 fn hello1_no_newline() -> TestResult {
-  run(&["Hello  there", "-n"], "tests/expected/hello1.n.txt")
+  run(&["-n", "Hello  there"], "tests/expected/hello1.n.txt")
 }
 
 #[test]
 fn hello2_no_newline() -> TestResult {
   run(&["-n", "Hello", "there"], "tests/expected/hello2.n.txt")
 }
+
+#[test]
+fn escapes_enabled() -> TestResult {
+  run(&["-e", "Hello\\tthere\\n"], "tests/expected/escapes.e.txt")
+}
+
+#[test]
+fn escapes_disabled() -> TestResult {
+  run(&["-E", "Hello\\tthere\\n"], "tests/expected/escapes.E.txt")?;
+  run(
+    &["-e", "-E", "Hello\\tthere\\n"],
+    "tests/expected/escapes.E.txt",
+  )
+}
+
+#[test]
+fn escapes_stop_output() -> TestResult {
+  run(
+    &["-e", "\\0101\\x42\\u00e9\\\\", "stop\\c", "ignored"],
+    "tests/expected/escapes.c.txt",
+  )
+}
+
+#[test]
+fn escapes_raw_bytes() -> TestResult {
+  Command::cargo_bin("echor")?
+    .args(["-e", "-n", "\\xff\\0376"])
+    .assert()
+    .stdout(predicate::eq(&[0xff_u8, 0xfe][..]));
+  Ok(())
+}
+
+#[test]
+fn posixly_correct() -> TestResult {
+  let expected = fs::read_to_string("tests/expected/posix.txt")?;
+  Command::cargo_bin("echor")?
+    .env("POSIXLY_CORRECT", "1")
+    .args(["-e", "Hello\\tthere", "-n"])
+    .assert()
+    .stdout(expected);
+  Ok(())
+}
+
+#[test]
+fn option_after_double_dash() -> TestResult {
+  Command::cargo_bin("echor")?
+    .args(["--", "-n"])
+    .assert()
+    .success()
+    .stdout("-- -n\n");
+  Ok(())
+}
+
+#[test]
+fn option_after_text() -> TestResult {
+  Command::cargo_bin("echor")?
+    .args(["-e", "a\\tb", "-n", "-V"])
+    .assert()
+    .success()
+    .stdout("a\tb -n -V\n");
+  Ok(())
+}
+
+#[test]
+fn unknown_option_is_text() -> TestResult {
+  Command::cargo_bin("echor")?
+    .args(["-x", "-n", "Hello"])
+    .assert()
+    .success()
+    .stdout("-x -n Hello\n");
+  Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn non_utf8_args() -> TestResult {
+  use std::ffi::OsStr;
+  use std::os::unix::ffi::OsStrExt;
+
+  Command::cargo_bin("echor")?
+    .arg(OsStr::from_bytes(b"caf\xe9"))
+    .assert()
+    .success()
+    .stdout(predicate::eq(&b"caf\xe9\n"[..]));
+  Ok(())
+}
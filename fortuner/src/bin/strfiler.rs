@@ -0,0 +1,80 @@
+use anyhow::{anyhow, Result};
+use clap::Parser;
+use fortuner::strfile::{self, Index, STR_ROTATED};
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::PathBuf;
+
+/// strfile command with Rust: build the random access index of a fortune file
+#[derive(Debug, Parser)]
+#[command(version, author, about)]
+struct Config {
+    /// Fortune file
+    #[arg(value_name = "FILE")]
+    source: PathBuf,
+    /// Index file [default: FILE.dat]
+    #[arg(value_name = "DATFILE")]
+    output: Option<PathBuf>,
+    /// Character that delimits the strings
+    #[arg(short = 'c', value_name = "CHAR", default_value = "%", value_parser = parse_delim)]
+    delim: u8,
+    /// Do not print a summary
+    #[arg(short, long)]
+    silent: bool,
+    /// Mark the strings as rot13 encoded
+    #[arg(short = 'x', long)]
+    rotated: bool,
+}
+
+fn parse_delim(val: &str) -> Result<u8, String> {
+    match val.as_bytes() {
+        [c] if c.is_ascii() => Ok(*c),
+        _ => Err("expected a single ASCII character".to_string()),
+    }
+}
+
+fn main() {
+    let config = Config::parse();
+
+    if let Err(e) = run(config) {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+}
+
+fn run(config: Config) -> Result<()> {
+    let source = &config.source;
+    let file = File::open(source).map_err(|e| anyhow!("{}: {e}", source.display()))?;
+    let mut index = Index::build(BufReader::new(file), config.delim)
+        .map_err(|e| anyhow!("{}: {e}", source.display()))?;
+    if config.rotated {
+        index.header.flags |= STR_ROTATED;
+    }
+
+    let output = config.output.unwrap_or_else(|| strfile::dat_path(source));
+    File::create(&output)
+        .and_then(|file| index.write(BufWriter::new(file)))
+        .map_err(|e| anyhow!("{}: {e}", output.display()))?;
+
+    if !config.silent {
+        let header = &index.header;
+        let plural = |n: u32| if n == 1 { "" } else { "s" };
+        println!("\"{}\" created", output.display());
+        if header.numstr == 1 {
+            println!("There was 1 string");
+        } else {
+            println!("There were {} strings", header.numstr);
+        }
+        println!(
+            "Longest string: {} byte{}",
+            header.longlen,
+            plural(header.longlen)
+        );
+        println!(
+            "Shortest string: {} byte{}",
+            header.shortlen,
+            plural(header.shortlen)
+        );
+    }
+    Ok(())
+}
@@ -1,31 +1,109 @@
-use std::io::{BufRead, BufReader};
-use std::path::PathBuf;
+use std::io::{BufRead, BufReader, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
 use anyhow::{anyhow, Result, bail};
+use rand::distributions::{Distribution, WeightedIndex};
 use rand::rngs::StdRng;
-use rand::seq::SliceRandom;
+use rand::seq::IteratorRandom;
 use rand::{RngCore, SeedableRng};
 use std::ffi::OsStr;
 use std::fs::{self, File};
 use walkdir::WalkDir;
 use clap::Parser;
 use regex::RegexBuilder;
+use strfile::{Index, STR_ROTATED};
+
+pub mod strfile;
 
 /// fortune command with Rust
 #[derive(Debug, Parser)]
 #[command(version, author, about)]
 pub struct Config {
-    /// Input file(s) or directory(s)
+    /// Input file(s) or directory(s), each optionally preceded by the
+    /// percentage of fortunes to pick from it, as in "30% jokes"
     #[arg(value_name = "FILE", required = true)]
     sources: Vec<String>,
     /// pattern to search for
     #[arg(short = 'm', long, value_name = "PATTERN")]
     pattern: Option<String>,
     /// seed for random number generator
-    #[arg(short, long, value_name = "SEED")]
+    #[arg(long, value_name = "SEED")]
     seed: Option<u64>,
     /// Case insensitive pattern matching
     #[arg(short, long)]
     insensitive: bool,
+    /// Pick every file with the same probability instead of by size
+    #[arg(short, long)]
+    equal: bool,
+    /// Print the files with their probabilities instead of a fortune
+    #[arg(short, long, conflicts_with = "pattern")]
+    files: bool,
+    /// Short fortunes only
+    #[arg(short, long, conflicts_with = "long")]
+    short: bool,
+    /// Long fortunes only
+    #[arg(short, long)]
+    long: bool,
+    /// Longest fortune in bytes that counts as short
+    #[arg(short = 'n', long, value_name = "LENGTH", default_value = "160")]
+    length: usize,
+    /// Offensive fortunes only
+    #[arg(short, long, conflicts_with = "all")]
+    offensive: bool,
+    /// Offensive and other fortunes alike
+    #[arg(short, long)]
+    all: bool,
+}
+
+/// Which fortunes fit the -s and -l options, by their length in bytes
+#[derive(Debug, Clone, Copy)]
+enum Length {
+    Any,
+    Short(usize),
+    Long(usize),
+}
+
+impl Length {
+    fn fits(self, len: usize) -> bool {
+        match self {
+            Length::Any => true,
+            Length::Short(max) => len <= max,
+            Length::Long(max) => len > max,
+        }
+    }
+
+    /// Whether a file has any fortune that fits, going by its index
+    fn fits_index(self, index: &Index) -> bool {
+        match self {
+            Length::Any => true,
+            Length::Short(_) => self.fits(index.header.shortlen as usize),
+            Length::Long(_) => self.fits(index.header.longlen as usize),
+        }
+    }
+}
+
+/// Which files the -o and -a options let through
+#[derive(Debug, Clone, Copy)]
+enum Offense {
+    Clean,
+    Offensive,
+    All,
+}
+
+impl Offense {
+    fn allows(self, source: &Source) -> bool {
+        match self {
+            Offense::Clean => !source.is_offensive(),
+            Offense::Offensive => source.is_offensive(),
+            Offense::All => true,
+        }
+    }
+}
+
+/// A source on the command line, with the probability given to it
+#[derive(Debug, PartialEq)]
+struct Spec {
+    percent: Option<u32>,
+    path: String,
 }
 
 #[derive(Debug)]
@@ -34,6 +112,55 @@ struct Fortune {
     text: String,
 }
 
+/// A fortune file along with its index, read from its ".dat" file when
+/// there is an up to date one
+#[derive(Debug)]
+struct Source {
+    path: PathBuf,
+    index: Index,
+    /// Probability of picking this file, in percent
+    percent: f64,
+}
+
+/// The files found under one `Spec`
+#[derive(Debug)]
+struct Group {
+    path: String,
+    given: Option<u32>,
+    percent: f64,
+    sources: Vec<Source>,
+}
+
+impl Group {
+    fn weight(&self, equal: bool) -> f64 {
+        self.sources.iter().map(|s| s.weight(equal)).sum()
+    }
+}
+
+impl Source {
+    /// Whether the strings are rot13 encoded, i.e. `strfile -x` was used
+    fn is_rotated(&self) -> bool {
+        self.index.header.flags & STR_ROTATED != 0
+    }
+
+    /// Offensive collections are named "*-o" or hidden with rot13
+    fn is_offensive(&self) -> bool {
+        self.is_rotated()
+            || self
+                .path
+                .file_name()
+                .is_some_and(|name| name.to_string_lossy().ends_with("-o"))
+    }
+
+    fn weight(&self, equal: bool) -> f64 {
+        match self.index.len() {
+            0 => 0.0,
+            _ if equal => 1.0,
+            len => len as f64,
+        }
+    }
+}
+
 pub fn run(config: Config) -> Result<()> {
     let pattern = config.pattern.map(|val: String| {
         RegexBuilder::new(val.as_str())
@@ -42,11 +169,38 @@ pub fn run(config: Config) -> Result<()> {
             .map_err(|_| anyhow!(r#"Invalid --pattern "{val}""#))
     })
     .transpose()?;
-    let paths = find_files(&config.sources)?;
-    let fortunes = read_fortunes(&paths)?;
-    
-    if pattern.is_some() {
-        let pattern = pattern.unwrap();
+    let specs = parse_specs(&config.sources)?;
+    let length = if config.short {
+        Length::Short(config.length)
+    } else if config.long {
+        Length::Long(config.length)
+    } else {
+        Length::Any
+    };
+    let offense = if config.all {
+        Offense::All
+    } else if config.offensive {
+        Offense::Offensive
+    } else {
+        Offense::Clean
+    };
+    let keep = |s: &Source| offense.allows(s) && length.fits_index(&s.index);
+
+    if let Some(pattern) = pattern {
+        let paths: Vec<String> = specs.into_iter().map(|s| s.path).collect();
+        let paths = find_files(&paths)?;
+        let mut fortunes = vec![];
+        for source in load_sources(&paths)?.iter().filter(|s| keep(s)) {
+            for mut fortune in read_fortunes(std::slice::from_ref(&source.path))? {
+                if source.is_rotated() {
+                    fortune.text = rot13(&fortune.text);
+                }
+                // 長さは strfile と同じく最後の改行を含めて数える
+                if length.fits(fortune.text.len() + 1) {
+                    fortunes.push(fortune);
+                }
+            }
+        }
         let filtered_fortunes: Vec<Fortune> = fortunes
             .into_iter()
             .filter(|f| pattern.is_match(&f.text))
@@ -66,16 +220,55 @@ pub fn run(config: Config) -> Result<()> {
             println!("{}\n%", f.text);
         });
     } else {
+        let groups = load_groups(&specs, config.equal, keep)?;
+        if config.files {
+            print_groups(&groups);
+            return Ok(());
+        }
+        let sources: Vec<Source> =
+            groups.into_iter().flat_map(|g| g.sources).collect();
         println!(
             "{}",
-            pick_fortune(&fortunes, config.seed)
-                .or_else(|| Some("No fortunes found".to_string())).unwrap()
+            pick_fortune(&sources, config.seed, length)?
+                .unwrap_or_else(|| "No fortunes found".to_string())
         );
     }
 
     Ok(())
 }
 
+/// Split arguments like "30%", "jokes" or "30%jokes" into sources with
+/// their percentages
+fn parse_specs(args: &[String]) -> Result<Vec<Spec>> {
+    let mut specs = vec![];
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        let digits = arg.bytes().take_while(u8::is_ascii_digit).count();
+        let spec = match arg[digits..].strip_prefix('%') {
+            Some(rest) if digits > 0 => {
+                let percent: u32 = arg[..digits]
+                    .parse()
+                    .ok()
+                    .filter(|&p| p <= 100)
+                    .ok_or_else(|| anyhow!(r#"Invalid percentage "{arg}""#))?;
+                let path = match rest {
+                    "" => args
+                        .next()
+                        .ok_or_else(|| anyhow!("{arg}: missing file"))?
+                        .to_string(),
+                    rest => rest.to_string(),
+                };
+                Spec { percent: Some(percent), path }
+            }
+            _ => Spec { percent: None, path: arg.to_string() },
+        };
+        specs.push(spec);
+    }
+
+    Ok(specs)
+}
+
 fn find_files(paths: &[String]) -> Result<Vec<PathBuf>> {
     let dat = OsStr::new("dat");
     let mut files = vec![];
@@ -131,12 +324,183 @@ fn read_fortunes(paths: &[PathBuf]) -> Result<Vec<Fortune>> {
     Ok(fortunes)
 }
 
-fn pick_fortune(fortunes: &[Fortune], seed: Option<u64>) -> Option<String> {
+/// Load the files of every spec and work out their probabilities like BSD
+/// fortune: the percentages left over by the specs that give one are
+/// shared among the other files by their number of fortunes, or evenly
+/// with `equal`. A spec with a percentage shares it among its own files
+/// the same way. Files turned down by `keep` get no share at all.
+fn load_groups(
+    specs: &[Spec],
+    equal: bool,
+    keep: impl Fn(&Source) -> bool,
+) -> Result<Vec<Group>> {
+    let mut groups = vec![];
+    for spec in specs {
+        let paths = find_files(std::slice::from_ref(&spec.path))?;
+        let mut sources = load_sources(&paths)?;
+        sources.retain(&keep);
+        groups.push(Group {
+            path: spec.path.clone(),
+            given: spec.percent,
+            percent: 0.0,
+            sources,
+        });
+    }
+
+    let given: u32 = groups.iter().filter_map(|g| g.given).sum();
+    let rest: f64 = groups
+        .iter()
+        .filter(|g| g.given.is_none())
+        .map(|g| g.weight(equal))
+        .sum();
+    if given > 100 || (given > 0 && given < 100 && rest == 0.0) {
+        bail!("Probabilities sum to {given}%");
+    }
+
+    for group in &mut groups {
+        let weight = group.weight(equal);
+        group.percent = match group.given {
+            Some(_) if weight == 0.0 => bail!("{}: No fortunes found", group.path),
+            Some(percent) => f64::from(percent),
+            None if weight == 0.0 => 0.0,
+            None => f64::from(100 - given) * weight / rest,
+        };
+        for source in &mut group.sources {
+            source.percent = match weight {
+                0.0 => 0.0,
+                _ => group.percent * source.weight(equal) / weight,
+            };
+        }
+    }
+
+    Ok(groups)
+}
+
+/// List the groups and, for directories, the files with fortunes in them
+fn print_groups(groups: &[Group]) {
+    for group in groups {
+        eprintln!("{:5.2}% {}", group.percent, group.path);
+        if Path::new(&group.path).is_dir() {
+            for source in group.sources.iter().filter(|s| !s.index.is_empty()) {
+                eprintln!(
+                    "    {:5.2}% {}",
+                    source.percent,
+                    source.path.file_name().unwrap().to_string_lossy()
+                );
+            }
+        }
+    }
+}
+
+fn load_sources(paths: &[PathBuf]) -> Result<Vec<Source>> {
+    paths
+        .iter()
+        .map(|path| {
+            let index = load_index(path)
+                .map_err(|e| anyhow!("{}: {e}", path.to_string_lossy()))?;
+            Ok(Source {
+                path: path.clone(),
+                index,
+                percent: 0.0,
+            })
+        })
+        .collect()
+}
+
+fn load_index(path: &Path) -> Result<Index> {
+    let file_len = fs::metadata(path)?.len();
+    let dat = strfile::dat_path(path);
+    if let Ok(file) = File::open(&dat) {
+        let index = Index::read(BufReader::new(file))
+            .map_err(|e| anyhow!("{}: {e}", dat.to_string_lossy()))?;
+        if index.is_current(file_len) {
+            return Ok(index);
+        }
+    }
+    // 索引がなければ区切りの位置だけを数える
+    Ok(Index::build(BufReader::new(File::open(path)?), b'%')?)
+}
+
+fn pick_fortune(
+    sources: &[Source],
+    seed: Option<u64>,
+    length: Length,
+) -> Result<Option<String>> {
     let mut rng: Box<dyn RngCore> = match seed {
         Some(seed) => Box::new(StdRng::seed_from_u64(seed)),
         None => Box::new(rand::thread_rng()),
     };
-    fortunes.choose(&mut rng).map(|f| f.text.to_string())
+    let candidates: Vec<&Source> = sources
+        .iter()
+        .filter(|s| s.percent > 0.0 && length.fits_index(&s.index))
+        .collect();
+    if candidates.is_empty() {
+        return Ok(None);
+    }
+    let mut weights =
+        WeightedIndex::new(candidates.iter().map(|s| s.percent))?;
+
+    // Like BSD fortune, pick again until the length fits. The index of a
+    // candidate says it has a fortune that does, but a stale index may be
+    // wrong, so every fortune is tried at most once.
+    let mut untried: Vec<Option<Vec<usize>>> = vec![None; candidates.len()];
+    let mut left = candidates.len();
+    while left > 0 {
+        let i = match candidates[..] {
+            [_] => 0,
+            _ => weights.sample(&mut rng),
+        };
+        let source = candidates[i];
+        let untried = untried[i]
+            .get_or_insert_with(|| (0..source.index.len()).collect());
+        let pos = (0..untried.len()).choose(&mut rng).unwrap();
+        let n = untried.swap_remove(pos);
+        if untried.is_empty() {
+            left -= 1;
+            if left > 0 {
+                weights.update_weights(&[(i, &0.0)])?;
+            }
+        }
+
+        let text = read_fortune(source, n)?;
+        if length.fits(text.len()) {
+            return Ok(Some(decode(source, &text)));
+        }
+    }
+    Ok(None)
+}
+
+/// Seek to the `n`th fortune of `source` and read only that one, as raw
+/// bytes so that its length is the one the index counts
+fn read_fortune(source: &Source, n: usize) -> Result<Vec<u8>> {
+    let read = || -> std::io::Result<Vec<u8>> {
+        let mut file = File::open(&source.path)?;
+        file.seek(SeekFrom::Start(source.index.offset(n)))?;
+        strfile::read_string(BufReader::new(file), source.index.header.delim)
+    };
+    read().map_err(|e| anyhow!("{}: {e}", source.path.to_string_lossy()))
+}
+
+/// The text of a fortune read from `source`, without its final newline
+/// and decoded when it is rot13 encoded
+fn decode(source: &Source, text: &[u8]) -> String {
+    let text = text.strip_suffix(b"\n").unwrap_or(text);
+    let text = String::from_utf8_lossy(text);
+    if source.is_rotated() {
+        rot13(&text)
+    } else {
+        text.into_owned()
+    }
+}
+
+fn rot13(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            'a'..='m' | 'A'..='M' => (c as u8 + 13) as char,
+            'n'..='z' | 'N'..='Z' => (c as u8 - 13) as char,
+            _ => c,
+        })
+        .collect()
 }
 
 #[cfg(test)]
@@ -152,7 +516,7 @@ mod unit_tests {
         let files = res.unwrap();
         assert_eq!(files.len(), 1);
         assert_eq!(
-            files.get(0).unwrap().to_string_lossy(),
+            files.first().unwrap().to_string_lossy(),
             "./tests/inputs/jokes"
         );
 
@@ -167,7 +531,7 @@ mod unit_tests {
         // Check number and order of files
         let files = res.unwrap();
         assert_eq!(files.len(), 5);
-        let first = files.get(0).unwrap().display().to_string();
+        let first = files.first().unwrap().display().to_string();
         assert!(first.contains("ascii-art"));
         let last = files.last().unwrap().display().to_string();
         assert!(last.contains("quotes"));
@@ -222,30 +586,153 @@ mod unit_tests {
     }
 
     #[test]
-    fn test_pick_fortune() {
-        // Create a slice of fortunes
-        let fortunes = &[
-            Fortune {
-                source: "fortunes".to_string(),
-                text: "You cannot achieve the impossible without \
-                        attempting the absurd."
-                    .to_string(),
-            },
-            Fortune {
-                source: "fortunes".to_string(),
-                text: "Assumption is the mother of all screw-ups."
-                    .to_string(),
-            },
-            Fortune {
-                source: "fortunes".to_string(),
-                text: "Neckties strangle clear thinking.".to_string(),
-            },
+    fn test_parse_specs() {
+        let args = ["30%", "jokes", "20%quotes", "literature", "7up"]
+            .map(String::from);
+        assert_eq!(
+            parse_specs(&args).unwrap(),
+            [
+                Spec { percent: Some(30), path: "jokes".to_string() },
+                Spec { percent: Some(20), path: "quotes".to_string() },
+                Spec { percent: None, path: "literature".to_string() },
+                Spec { percent: None, path: "7up".to_string() },
+            ]
+        );
+
+        assert!(parse_specs(&["101%".to_string(), "jokes".to_string()]).is_err());
+        assert!(parse_specs(&["10%".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_load_groups() {
+        let spec = |percent, path: &str| Spec {
+            percent,
+            path: format!("./tests/inputs/{path}"),
+        };
+        let percents = |groups: Vec<Group>| -> Vec<f64> {
+            groups
+                .iter()
+                .flat_map(|g| &g.sources)
+                .map(|s| (s.percent * 100.0).round() / 100.0)
+                .collect()
+        };
+
+        // 6 jokes and 5 quotes
+        let specs = [spec(None, "jokes"), spec(None, "quotes")];
+        let by_size = load_groups(&specs, false, |_| true).unwrap();
+        assert_eq!(percents(by_size), [54.55, 45.45]);
+        let equal = load_groups(&specs, true, |_| true).unwrap();
+        assert_eq!(percents(equal), [50.0, 50.0]);
+
+        // The rest is shared by the files without a percentage
+        let specs = [
+            spec(Some(40), "literature"),
+            spec(None, "jokes"),
+            spec(None, "quotes"),
         ];
+        assert_eq!(
+            percents(load_groups(&specs, false, |_| true).unwrap()),
+            [40.0, 32.73, 27.27]
+        );
+
+        let specs = [spec(Some(60), "jokes"), spec(Some(60), "quotes")];
+        assert!(load_groups(&specs, false, |_| true).is_err());
+        let specs = [spec(Some(60), "jokes")];
+        assert!(load_groups(&specs, false, |_| true).is_err());
+    }
+
+    #[test]
+    fn test_pick_fortune() {
+        let specs = [Spec {
+            percent: None,
+            path: "./tests/inputs/quotes".to_string(),
+        }];
+        let sources: Vec<Source> = load_groups(&specs, false, |_| true)
+            .unwrap()
+            .into_iter()
+            .flat_map(|g| g.sources)
+            .collect();
+        assert_eq!(sources[0].index.len(), 5);
 
         // Pick a fortune with a seed
         assert_eq!(
-            pick_fortune(fortunes, Some(1)).unwrap(),
-            "Neckties strangle clear thinking.".to_string()
+            pick_fortune(&sources, Some(1), Length::Any).unwrap().unwrap(),
+            "You can observe a lot just by watching.\n-- Yogi Berra"
+        );
+        assert_eq!(pick_fortune(&[], Some(1), Length::Any).unwrap(), None);
+        assert_eq!(pick_fortune(&sources, Some(1), Length::Long(250)).unwrap(), None);
+
+        // Only one of the quotes is up to 50 bytes long
+        assert_eq!(
+            pick_fortune(&sources, Some(1), Length::Short(50)).unwrap().unwrap(),
+            "It's like deja vu all over again.\n-- Yogi Berra"
         );
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_rot13() {
+        assert_eq!(
+            rot13("Why did the chicken cross the road?"),
+            "Jul qvq gur puvpxra pebff gur ebnq?"
+        );
+        assert_eq!(rot13(&rot13("Hello, World! 123")), "Hello, World! 123");
+    }
+
+    #[test]
+    fn test_load_index() {
+        let dir = std::env::temp_dir()
+            .join(format!("fortuner-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("fortunes");
+        fs::write(&path, "first\n%\nsecond\n%\nthird\n").unwrap();
+
+        // An index with its offsets shuffled is followed as is
+        let file = BufReader::new(File::open(&path).unwrap());
+        let mut index = Index::build(file, b'%').unwrap();
+        index.offsets[..3].reverse();
+        index.header.flags = strfile::STR_RANDOM;
+        index.write(File::create(strfile::dat_path(&path)).unwrap()).unwrap();
+        let source = Source {
+            index: load_index(&path).unwrap(),
+            path: path.clone(),
+            percent: 100.0,
+        };
+        assert_eq!(read_fortune(&source, 0).unwrap(), b"third\n");
+
+        // A stale index is ignored
+        fs::write(&path, "first\n%\nsecond\n").unwrap();
+        assert_eq!(load_index(&path).unwrap().len(), 2);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_pick_fortune_raw_length() {
+        let dir = std::env::temp_dir()
+            .join(format!("fortuner-raw-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("fortunes");
+        let source = |path: &Path| Source {
+            index: load_index(path).unwrap(),
+            path: path.to_path_buf(),
+            percent: 100.0,
+        };
+
+        // Invalid UTF-8 is measured before it is replaced with U+FFFD
+        fs::write(&path, b"\xff\xff\xff\n%\nabcdefghijklmnopqrstuvwxyz\n").unwrap();
+        let sources = [source(&path)];
+        assert_eq!(
+            pick_fortune(&sources, Some(1), Length::Short(5)).unwrap().unwrap(),
+            "\u{fffd}\u{fffd}\u{fffd}"
+        );
+
+        // An index that promises a short fortune that is not there
+        let mut index = sources[0].index.clone();
+        index.header.shortlen = 1;
+        index.write(File::create(strfile::dat_path(&path)).unwrap()).unwrap();
+        let sources = [source(&path)];
+        assert_eq!(pick_fortune(&sources, Some(1), Length::Short(3)).unwrap(), None);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,239 @@
+use std::ffi::OsString;
+use std::io::{self, BufRead, Read, Write};
+use std::path::{Path, PathBuf};
+
+/// Version written by `strfile` from fortune-mod
+pub const VERSION: u32 = 2;
+/// The offsets were shuffled
+pub const STR_RANDOM: u32 = 0x1;
+/// The offsets were sorted by string
+pub const STR_ORDERED: u32 = 0x2;
+/// The strings are rot13 encoded
+pub const STR_ROTATED: u32 = 0x4;
+
+const HEADER_LEN: usize = 24;
+
+/// Header of a `strfile` index, stored as big-endian 32-bit words with the
+/// delimiter in the first of the four trailing bytes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Header {
+    pub version: u32,
+    pub numstr: u32,
+    /// Length of the longest string in bytes, without its delimiter line
+    pub longlen: u32,
+    pub shortlen: u32,
+    pub flags: u32,
+    pub delim: u8,
+}
+
+/// A `strfile` index: the header followed by the offset of every string
+/// and the offset of the end of the file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Index {
+    pub header: Header,
+    pub offsets: Vec<u32>,
+}
+
+impl Index {
+    /// Scan a fortune file whose strings are separated by lines holding
+    /// only `delim`. Empty strings are skipped like `strfile` does.
+    pub fn build(mut reader: impl BufRead, delim: u8) -> io::Result<Index> {
+        let mut offsets = vec![];
+        let (mut longlen, mut shortlen) = (0, u32::MAX);
+        let (mut start, mut pos) = (0, 0);
+        let mut line = vec![];
+
+        loop {
+            line.clear();
+            let bytes = reader.read_until(b'\n', &mut line)? as u64;
+            if bytes == 0 || is_delim(&line, delim) {
+                if pos > start {
+                    let len = offset(pos - start)?;
+                    offsets.push(offset(start)?);
+                    longlen = longlen.max(len);
+                    shortlen = shortlen.min(len);
+                }
+                pos += bytes;
+                start = pos;
+                if bytes == 0 {
+                    break;
+                }
+            } else {
+                pos += bytes;
+            }
+        }
+        offsets.push(offset(pos)?);
+
+        Ok(Index {
+            header: Header {
+                version: VERSION,
+                numstr: offsets.len() as u32 - 1,
+                longlen,
+                shortlen: if offsets.len() > 1 { shortlen } else { 0 },
+                flags: 0,
+                delim,
+            },
+            offsets,
+        })
+    }
+
+    pub fn read(mut reader: impl Read) -> io::Result<Index> {
+        let mut bytes = vec![];
+        reader.read_to_end(&mut bytes)?;
+        if bytes.len() < HEADER_LEN {
+            return Err(invalid("truncated strfile header".to_string()));
+        }
+        let mut words = bytes
+            .chunks_exact(4)
+            .map(|w| u32::from_be_bytes([w[0], w[1], w[2], w[3]]));
+        let mut word = || words.next().unwrap_or_default();
+        let header = Header {
+            version: word(),
+            numstr: word(),
+            longlen: word(),
+            shortlen: word(),
+            flags: word(),
+            delim: bytes[20],
+        };
+        if !(1..=VERSION).contains(&header.version) {
+            return Err(invalid(format!(
+                "unsupported strfile version {}",
+                header.version
+            )));
+        }
+
+        let mut offsets: Vec<u32> = bytes[HEADER_LEN..]
+            .chunks_exact(4)
+            .map(|w| u32::from_be_bytes([w[0], w[1], w[2], w[3]]))
+            .collect();
+        if offsets.len() < header.numstr as usize {
+            return Err(invalid(format!(
+                "expected {} offsets, found {}",
+                header.numstr,
+                offsets.len()
+            )));
+        }
+        offsets.truncate(header.numstr as usize + 1);
+        Ok(Index { header, offsets })
+    }
+
+    pub fn write(&self, mut writer: impl Write) -> io::Result<()> {
+        let header = &self.header;
+        for word in [
+            header.version,
+            header.numstr,
+            header.longlen,
+            header.shortlen,
+            header.flags,
+        ] {
+            writer.write_all(&word.to_be_bytes())?;
+        }
+        writer.write_all(&[header.delim, 0, 0, 0])?;
+        for offset in &self.offsets {
+            writer.write_all(&offset.to_be_bytes())?;
+        }
+        writer.flush()
+    }
+
+    pub fn len(&self) -> usize {
+        self.header.numstr as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Offset of the `n`th string
+    pub fn offset(&self, n: usize) -> u64 {
+        u64::from(self.offsets[n])
+    }
+
+    /// Whether the index still describes a file of `file_len` bytes.
+    /// Only the end offset can tell, and some indexes leave it out.
+    pub fn is_current(&self, file_len: u64) -> bool {
+        self.offsets
+            .get(self.len())
+            .is_none_or(|&end| u64::from(end) == file_len)
+    }
+}
+
+/// Read one string up to the next delimiter line or the end of the file.
+/// Its final newline is kept, as the lengths in the header count it.
+pub fn read_string(mut reader: impl BufRead, delim: u8) -> io::Result<Vec<u8>> {
+    let mut string = vec![];
+    loop {
+        let start = string.len();
+        if reader.read_until(b'\n', &mut string)? == 0 {
+            break;
+        }
+        if is_delim(&string[start..], delim) {
+            string.truncate(start);
+            break;
+        }
+    }
+    Ok(string)
+}
+
+/// The index that belongs to `path`, i.e. `path` with ".dat" appended
+pub fn dat_path(path: &Path) -> PathBuf {
+    let mut dat = OsString::from(path);
+    dat.push(".dat");
+    dat.into()
+}
+
+fn is_delim(line: &[u8], delim: u8) -> bool {
+    let line = line.strip_suffix(b"\n").unwrap_or(line);
+    let line = line.strip_suffix(b"\r").unwrap_or(line);
+    line == [delim]
+}
+
+fn offset(pos: u64) -> io::Result<u32> {
+    u32::try_from(pos).map_err(|_| invalid("file too large for a strfile index".to_string()))
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read_string, Index};
+    use std::io::Cursor;
+
+    const FORTUNES: &[u8] = b"%\nfirst\n%\n%\nsecond\nline\n%\nthird";
+
+    #[test]
+    fn test_build() {
+        let index = Index::build(FORTUNES, b'%').unwrap();
+        assert_eq!(index.len(), 3);
+        assert_eq!(index.offsets, [2, 12, 26, 31]);
+        assert_eq!(index.header.longlen, 12);
+        assert_eq!(index.header.shortlen, 5);
+        assert!(index.is_current(FORTUNES.len() as u64));
+
+        let index = Index::build(&b""[..], b'%').unwrap();
+        assert!(index.is_empty());
+        assert_eq!(index.header.shortlen, 0);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let index = Index::build(FORTUNES, b'%').unwrap();
+        let mut bytes = vec![];
+        index.write(&mut bytes).unwrap();
+        assert_eq!(bytes.len(), 24 + 4 * 4);
+        assert_eq!(&bytes[..8], b"\0\0\0\x02\0\0\0\x03");
+        assert_eq!(Index::read(Cursor::new(bytes)).unwrap(), index);
+
+        assert!(Index::read(&b"\0\0\0\x02"[..]).is_err());
+    }
+
+    #[test]
+    fn test_read_string() {
+        let index = Index::build(FORTUNES, b'%').unwrap();
+        let string = |n| read_string(&FORTUNES[index.offset(n) as usize..], b'%').unwrap();
+        assert_eq!(string(0), b"first\n");
+        assert_eq!(string(1), b"second\nline\n");
+        assert_eq!(string(2), b"third");
+    }
+}
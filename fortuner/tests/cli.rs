@@ -11,6 +11,7 @@ const EMPTY_DIR: &str = "./tests/inputs/empty";
 const JOKES: &str = "./tests/inputs/jokes";
 const LITERATURE: &str = "./tests/inputs/literature";
 const QUOTES: &str = "./tests/inputs/quotes";
+const OFFENSIVE_DIR: &str = "./tests/offensive";
 
 // --------------------------------------------------
 fn random_string() -> String {
@@ -91,7 +92,7 @@ fn no_fortunes_found() -> Result<()> {
 #[test]
 fn quotes_seed_1() -> Result<()> {
     run(
-        &[QUOTES, "-s", "1"],
+        &[QUOTES, "--seed", "1"],
         "You can observe a lot just by watching.\n-- Yogi Berra\n",
     )
 }
@@ -100,7 +101,7 @@ fn quotes_seed_1() -> Result<()> {
 #[test]
 fn jokes_seed_1() -> Result<()> {
     run(
-        &[JOKES, "-s", "1"],
+        &[JOKES, "--seed", "1"],
         "Q: What happens when frogs park illegally?\nA: They get toad.\n",
     )
 }
@@ -108,10 +109,134 @@ fn jokes_seed_1() -> Result<()> {
 // --------------------------------------------------
 #[test]
 fn dir_seed_10() -> Result<()> {
+    // A file is picked by its share of all fortunes, then a fortune in it
     run(
-        &[FORTUNE_DIR, "-s", "10"],
-        "Q: Why did the fungus and the alga marry?\n\
-        A: Because they took a lichen to each other!\n",
+        &[FORTUNE_DIR, "--seed", "10"],
+        include_str!("expected/dir_seed_10.out"),
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn percent_seed_1() -> Result<()> {
+    // The quotes get what is left of 100%, i.e. nothing
+    run(
+        &["100%", JOKES, QUOTES, "--seed", "1"],
+        "Q: What happens when frogs park illegally?\nA: They get toad.\n",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_percent() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["60%", JOKES, "50%", QUOTES])
+        .assert()
+        .failure()
+        .stderr("Probabilities sum to 110%\n");
+    Command::cargo_bin(PRG)?
+        .args(["101%", JOKES])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(r#"Invalid percentage "101%""#));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn list_files() -> Result<()> {
+    run_outfiles(
+        &["-f", "20%", JOKES, FORTUNE_DIR],
+        "tests/expected/files.out",
+        "tests/expected/files.err",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn list_files_equal() -> Result<()> {
+    run_outfiles(
+        &["-f", "-e", "20%", JOKES, FORTUNE_DIR],
+        "tests/expected/files_equal.out",
+        "tests/expected/files_equal.err",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn short_only() -> Result<()> {
+    run(
+        &[OFFENSIVE_DIR, "-s", "-n", "30", "--seed", "1"],
+        "Clean jokes only here.\n",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn long_only() -> Result<()> {
+    run(
+        &[OFFENSIVE_DIR, "-l", "-n", "30", "--seed", "1"],
+        "A second, somewhat longer clean fortune that should not count \
+        as short at all.\n",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn no_short_offensive() -> Result<()> {
+    run(&[OFFENSIVE_DIR, "-o", "-s", "-n", "10"], "No fortunes found\n")
+}
+
+// --------------------------------------------------
+fn list_offense(flag: &str, expected: &'static str) -> Result<()> {
+    let mut args = vec!["-f", OFFENSIVE_DIR];
+    args.extend((!flag.is_empty()).then_some(flag));
+    Command::cargo_bin(PRG)?
+        .args(args)
+        .assert()
+        .success()
+        .stdout("")
+        .stderr(expected);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn skips_offensive() -> Result<()> {
+    list_offense("", "100.00% ./tests/offensive\n    100.00% clean\n")
+}
+
+// --------------------------------------------------
+#[test]
+fn offensive_only() -> Result<()> {
+    list_offense(
+        "-o",
+        "100.00% ./tests/offensive\n    66.67% rot\n    33.33% rude-o\n",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn offensive_all() -> Result<()> {
+    list_offense(
+        "-a",
+        "100.00% ./tests/offensive\n    40.00% clean\n    \
+        40.00% rot\n    20.00% rude-o\n",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn rot13_decoded() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["-o", "-i", "-m", "hidden", OFFENSIVE_DIR])
+        .assert()
+        .success()
+        .stdout("Hidden with rot13.\n%\nAnother hidden fortune.\n%\n")
+        .stderr("(rot)\n%\n");
+    run(
+        &["-o", "100%", "./tests/offensive/rot", "--seed", "1"],
+        "Another hidden fortune.\n",
     )
 }
 
@@ -202,3 +327,48 @@ fn mark_twain_lower_i() -> Result<()> {
         "tests/expected/twain_lower_i.err",
     )
 }
+
+// --------------------------------------------------
+#[test]
+fn quotes_dat_seed_1() -> Result<()> {
+    // The index next to the quotes must point at the same fortune
+    assert!(fs::metadata("./tests/inputs/quotes.dat").is_ok());
+    run(
+        &["./tests/inputs/quotes", "--seed", "1"],
+        "You can observe a lot just by watching.\n-- Yogi Berra\n",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn strfiler_jokes() -> Result<()> {
+    let outfile = std::env::temp_dir().join(format!("{}.dat", random_string()));
+    let outfile = outfile.to_string_lossy().into_owned();
+    let expected = format!(
+        "\"{outfile}\" created\nThere were 6 strings\n\
+        Longest string: 100 bytes\nShortest string: 61 bytes\n"
+    );
+    Command::cargo_bin("strfiler")?
+        .args([JOKES, &outfile])
+        .assert()
+        .success()
+        .stdout(expected);
+
+    let index = fs::read(&outfile)?;
+    fs::remove_file(&outfile)?;
+    assert_eq!(index, fs::read("tests/expected/jokes.dat")?);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn strfiler_dies_bad_delim() -> Result<()> {
+    Command::cargo_bin("strfiler")?
+        .args(["-c", "%%", JOKES])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "expected a single ASCII character",
+        ));
+    Ok(())
+}
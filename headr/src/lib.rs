@@ -1,7 +1,9 @@
 use clap::Parser;
+use std::collections::VecDeque;
 use std::error::Error;
 use std::fs::File;
-use std::io::{self, BufRead, BufReader, Read};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::num::ParseIntError;
 
 type MyResult<T> = Result<T, Box<dyn Error>>;
 
@@ -12,45 +14,144 @@ pub struct Config {
   /// Input file(s)
   #[arg(value_name = "FILE", default_value = "-")]
   files: Vec<String>,
-  /// Number of lines
+  /// Number of lines; with a leading '-', all but the last LINES lines
   #[arg(
     long,
     short = 'n',
     value_name = "LINES",
     default_value = "10",
-    value_parser = clap::value_parser!(u64).range(1..),
+    allow_hyphen_values = true,
+    value_parser = parse_count,
   )]
-  lines: u64,
-  /// Number of bytes
-  #[arg(long, short = 'c', value_name = "BYTES", conflicts_with = "lines")]
-  bytes: Option<usize>,
+  lines: Count,
+  /// Number of bytes; with a leading '-', all but the last BYTES bytes
+  #[arg(
+    long,
+    short = 'c',
+    value_name = "BYTES",
+    conflicts_with = "lines",
+    allow_hyphen_values = true,
+    value_parser = parse_count,
+  )]
+  bytes: Option<Count>,
+  /// Number of UTF-8 characters; invalid bytes count as one character each
+  #[arg(
+    long,
+    value_name = "CHARS",
+    conflicts_with_all = ["lines", "bytes"],
+    value_parser = parse_size,
+  )]
+  chars: Option<u64>,
+  /// Never print headers giving file names
+  #[arg(
+    long,
+    short = 'q',
+    visible_alias = "silent",
+    overrides_with = "verbose"
+  )]
+  quiet: bool,
+  /// Always print headers giving file names
+  #[arg(long, short = 'v', overrides_with = "quiet")]
+  verbose: bool,
+  /// Line delimiter is NUL, not newline
+  #[arg(long, short = 'z')]
+  zero_terminated: bool,
+}
+
+/// How much of each file to print
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Count {
+  /// The first N lines or bytes
+  First(u64),
+  /// Everything except the last N lines or bytes
+  AllButLast(u64),
+}
+
+fn parse_count(val: &str) -> Result<Count, String> {
+  match val.strip_prefix('-') {
+    Some(n) => Ok(Count::AllButLast(parse_size(n)?)),
+    None => Ok(Count::First(parse_size(val)?)),
+  }
+}
+
+/// Parse a number with an optional multiplier suffix like GNU head:
+/// b (512), kB/K/KiB, MB/M/MiB, GB/G/GiB and so on up to E, with k and m
+/// also accepted in lower case
+fn parse_size(val: &str) -> Result<u64, String> {
+  let digits = val.len() - val.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+  let (number, suffix) = val.split_at(digits);
+  let multiplier = match suffix {
+    "" => 1,
+    "b" => 512,
+    _ => {
+      let (unit, base) = match suffix.len() {
+        1 => (suffix, 1024_u64),
+        2 if suffix.ends_with('B') => (&suffix[..1], 1000),
+        3 if suffix.ends_with("iB") => (&suffix[..1], 1024),
+        _ => (suffix, 1024),
+      };
+      // GNU head と同じく k と m は小文字も受け付ける
+      let unit = match unit {
+        "k" => "K",
+        "m" => "M",
+        unit => unit,
+      };
+      match ["K", "M", "G", "T", "P", "E"]
+        .iter()
+        .position(|u| *u == unit)
+      {
+        Some(power) => base.pow(power as u32 + 1),
+        // "+2" のように u64 としては読めてしまう値もここで弾く
+        None => return Err("invalid digit found in string".to_string()),
+      }
+    }
+  };
+  let number: u64 = number.parse().map_err(|e: ParseIntError| e.to_string())?;
+  number
+    .checked_mul(multiplier)
+    .ok_or_else(|| "number too large to fit in target type".to_string())
 }
 
 pub fn run(config: Config) -> MyResult<()> {
   let num_files = config.files.len();
+  let delimiter = if config.zero_terminated { b'\0' } else { b'\n' };
+  let mut out = io::stdout();
 
   for (file_num, filename) in config.files.iter().enumerate() {
     match open(filename) {
       Err(e) => eprintln!("{}: {}", filename, e),
       Ok(mut file) => {
-        if num_files > 1 {
+        if config.verbose || (!config.quiet && num_files > 1) {
           println!(
             "{}==> {} <==",
             if file_num > 0 { "\n" } else { "" },
             filename
           );
         }
-        if let Some(num_bytes) = config.bytes {
-          let bytes: Result<Vec<_>, _> = file.bytes().take(num_bytes).collect();
-          print!("{}", String::from_utf8_lossy(&bytes?));
-        } else {
-          let mut line = String::new();
-          for _ in 0..config.lines {
-            if file.read_line(&mut line)? == 0 {
-              break;
+        match (config.chars, config.bytes, config.lines) {
+          (Some(num_chars), _, _) => {
+            head_chars(&mut file, num_chars, &mut out)?;
+          }
+          (None, Some(Count::First(num_bytes)), _) => {
+            io::copy(&mut file.take(num_bytes), &mut out)?;
+            out.flush()?;
+          }
+          (None, Some(Count::AllButLast(num_bytes)), _) => {
+            all_but_last_bytes(&mut file, num_bytes as usize, &mut out)?;
+          }
+          (None, None, Count::First(num_lines)) => {
+            let mut line = Vec::new();
+            for _ in 0..num_lines {
+              if file.read_until(delimiter, &mut line)? == 0 {
+                break;
+              }
+              out.write_all(&line)?;
+              line.clear();
             }
-            print!("{}", line);
-            line.clear();
+            out.flush()?;
+          }
+          (None, None, Count::AllButLast(num_lines)) => {
+            all_but_last_lines(&mut file, num_lines as usize, delimiter, &mut out)?;
           }
         }
       }
@@ -59,6 +160,88 @@ pub fn run(config: Config) -> MyResult<()> {
   Ok(())
 }
 
+/// Copy the first `n` characters of `file` to `out` without decoding them.
+/// A byte that does not start a valid UTF-8 sequence, or a sequence cut
+/// short, counts as one character and is copied as it is.
+fn head_chars(file: &mut impl BufRead, n: u64, out: &mut impl Write) -> io::Result<()> {
+  let mut ch = Vec::with_capacity(4);
+  for _ in 0..n {
+    let Some(&lead) = file.fill_buf()?.first() else {
+      break;
+    };
+    file.consume(1);
+    ch.clear();
+    ch.push(lead);
+    let width = match lead {
+      0xc2..=0xdf => 2,
+      0xe0..=0xef => 3,
+      0xf0..=0xf4 => 4,
+      _ => 1,
+    };
+    while ch.len() < width {
+      match file.fill_buf()?.first() {
+        Some(&byte) if byte & 0xc0 == 0x80 => {
+          file.consume(1);
+          ch.push(byte);
+        }
+        _ => break,
+      }
+    }
+    out.write_all(&ch)?;
+  }
+  out.flush()
+}
+
+/// Copy `file` to `out` except for its last `n` bytes, which are held back
+/// in a buffer of at most `n` bytes until the end of input is seen
+fn all_but_last_bytes(file: &mut impl BufRead, n: usize, out: &mut impl Write) -> io::Result<()> {
+  let mut held = VecDeque::new();
+  loop {
+    let chunk = file.fill_buf()?;
+    if chunk.is_empty() {
+      break;
+    }
+    let len = chunk.len();
+    // 窓からあふれた分だけ、古い順に書き出す
+    let excess = (held.len() + len).saturating_sub(n);
+    let from_held = excess.min(held.len());
+    let (front, back) = held.as_slices();
+    let front_len = from_held.min(front.len());
+    out.write_all(&front[..front_len])?;
+    out.write_all(&back[..from_held - front_len])?;
+    held.drain(..from_held);
+    let from_chunk = excess - from_held;
+    out.write_all(&chunk[..from_chunk])?;
+    held.extend(&chunk[from_chunk..]);
+    file.consume(len);
+  }
+  out.flush()
+}
+
+/// Copy `file` to `out` except for its last `n` lines ending with
+/// `delimiter`, keeping at most `n` lines in memory
+fn all_but_last_lines(
+  file: &mut impl BufRead,
+  n: usize,
+  delimiter: u8,
+  out: &mut impl Write,
+) -> io::Result<()> {
+  let mut held: VecDeque<Vec<u8>> = VecDeque::new();
+  loop {
+    let mut line = Vec::new();
+    if file.read_until(delimiter, &mut line)? == 0 {
+      break;
+    }
+    held.push_back(line);
+    if held.len() > n {
+      if let Some(line) = held.pop_front() {
+        out.write_all(&line)?;
+      }
+    }
+  }
+  out.flush()
+}
+
 // fn parse_positive_int(val: &str) -> MyResult<usize> {
 //   match val.parse() {
 //     Ok(n) if n > 0 => Ok(n),
@@ -87,3 +270,39 @@ fn open(filename: &str) -> MyResult<Box<dyn BufRead>> {
 //   assert!(res.is_err());
 //   assert_eq!(res.unwrap_err().to_string(), "0".to_string());
 // }
+
+#[test]
+fn test_parse_size() {
+  assert_eq!(parse_size("3"), Ok(3));
+  assert_eq!(parse_size("2b"), Ok(1024));
+  assert_eq!(parse_size("1K"), Ok(1024));
+  assert_eq!(parse_size("1k"), Ok(1024));
+  assert_eq!(parse_size("1kB"), Ok(1000));
+  assert_eq!(parse_size("2KiB"), Ok(2048));
+  assert_eq!(parse_size("1MB"), Ok(1_000_000));
+  assert_eq!(parse_size("1G"), Ok(1 << 30));
+  assert_eq!(parse_size("1E"), Ok(1 << 60));
+  assert_eq!(
+    parse_size("foo"),
+    Err("invalid digit found in string".to_string())
+  );
+  assert_eq!(
+    parse_size("3Q"),
+    Err("invalid digit found in string".to_string())
+  );
+  assert_eq!(
+    parse_size("+2"),
+    Err("invalid digit found in string".to_string())
+  );
+  assert_eq!(
+    parse_count("-+1"),
+    Err("invalid digit found in string".to_string())
+  );
+  assert_eq!(
+    parse_size("16E"),
+    Err("number too large to fit in target type".to_string())
+  );
+
+  assert_eq!(parse_count("-2K"), Ok(Count::AllButLast(2048)));
+  assert_eq!(parse_count("0"), Ok(Count::First(0)));
+}
@@ -3,8 +3,7 @@ use assert_cmd::Command;
 use predicates::prelude::*;
 use pretty_assertions::assert_eq;
 use rand::{distributions::Alphanumeric, Rng};
-use std::fs::{self, File};
-use std::io::prelude::*;
+use std::fs;
 
 const PRG: &str = "headr";
 const EMPTY: &str = "./tests/inputs/empty.txt";
@@ -12,6 +11,8 @@ const ONE: &str = "./tests/inputs/one.txt";
 const TWO: &str = "./tests/inputs/two.txt";
 const THREE: &str = "./tests/inputs/three.txt";
 const TWELVE: &str = "./tests/inputs/twelve.txt";
+const ZERO: &str = "./tests/inputs/zero.txt";
+const BINARY: &str = "./tests/inputs/binary.bin";
 
 // --------------------------------------------------
 fn random_string() -> String {
@@ -97,15 +98,10 @@ fn skips_bad_file() -> Result<()> {
 
 // --------------------------------------------------
 fn run(args: &[&str], expected_file: &str) -> Result<()> {
-    // Extra work here due to lossy UTF
-    let mut file = File::open(expected_file)?;
-    let mut buffer = Vec::new();
-    file.read_to_end(&mut buffer)?;
-    let expected = String::from_utf8_lossy(&buffer);
-
+    let expected = fs::read(expected_file)?;
     let output = Command::cargo_bin(PRG)?.args(args).output().expect("fail");
     assert!(output.status.success());
-    assert_eq!(String::from_utf8_lossy(&output.stdout), expected);
+    assert_eq!(output.stdout, expected);
 
     Ok(())
 }
@@ -116,12 +112,8 @@ fn run_stdin(
     input_file: &str,
     expected_file: &str,
 ) -> Result<()> {
-    // Extra work here due to lossy UTF
-    let mut file = File::open(expected_file)?;
-    let mut buffer = Vec::new();
-    file.read_to_end(&mut buffer)?;
-    let expected = String::from_utf8_lossy(&buffer);
-    let input = fs::read_to_string(input_file)?;
+    let expected = fs::read(expected_file)?;
+    let input = fs::read(input_file)?;
 
     let output = Command::cargo_bin(PRG)?
         .write_stdin(input)
@@ -129,7 +121,7 @@ fn run_stdin(
         .output()
         .expect("fail");
     assert!(output.status.success());
-    assert_eq!(String::from_utf8_lossy(&output.stdout), expected);
+    assert_eq!(output.stdout, expected);
 
     Ok(())
 }
@@ -423,3 +415,174 @@ fn multiple_files_c4() -> Result<()> {
         "tests/expected/all.c4.out",
     )
 }
+
+// --------------------------------------------------
+#[test]
+fn three_all_but_last_lines() -> Result<()> {
+    run(&[THREE, "-n", "-3"], "tests/expected/three.txt.n-3.out")
+}
+
+#[test]
+fn three_all_but_last_bytes() -> Result<()> {
+    run(&[THREE, "-c", "-5"], "tests/expected/three.txt.c-5.out")
+}
+
+#[test]
+fn twelve_all_but_last_lines() -> Result<()> {
+    run(&[TWELVE, "-n", "-3"], "tests/expected/twelve.txt.n-3.out")
+}
+
+#[test]
+fn twelve_all_but_last_bytes() -> Result<()> {
+    run(&[TWELVE, "-c", "-5"], "tests/expected/twelve.txt.c-5.out")
+}
+
+#[test]
+fn twelve_all_but_last_lines_stdin() -> Result<()> {
+    run_stdin(&["-n", "-3"], TWELVE, "tests/expected/twelve.txt.n-3.out")
+}
+
+#[test]
+fn twelve_all_but_last_bytes_stdin() -> Result<()> {
+    run_stdin(&["-c", "-5"], TWELVE, "tests/expected/twelve.txt.c-5.out")
+}
+
+#[test]
+fn all_but_last_zero() -> Result<()> {
+    run(&[TWELVE, "-n", "-0"], TWELVE)?;
+    run(&[TWELVE, "--bytes=-0"], TWELVE)
+}
+
+#[test]
+fn multiple_files_all_but_last_lines() -> Result<()> {
+    run(
+        &[EMPTY, ONE, TWO, THREE, TWELVE, "-n", "-2"],
+        "tests/expected/all.n-2.out",
+    )
+}
+
+#[test]
+fn multiple_files_all_but_last_bytes() -> Result<()> {
+    run(
+        &["-c", "-3", EMPTY, ONE, TWO, THREE, TWELVE],
+        "tests/expected/all.c-3.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn size_suffixes() -> Result<()> {
+    run(&[TWELVE, "-n", "1K"], TWELVE)?;
+    run(&[TWELVE, "-c", "1b"], TWELVE)?;
+    run(&[TWELVE, "--bytes=1kB"], TWELVE)?;
+    run(&[TWELVE, "-n", "-1KiB"], EMPTY)
+}
+
+#[test]
+fn dies_bad_suffix() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["-c", "1X", EMPTY])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "invalid value '1X' for '--bytes <BYTES>'",
+        ));
+    Ok(())
+}
+
+#[test]
+fn dies_plus_sign() -> Result<()> {
+    for (flag, val, name) in [
+        ("-n", "+2", "--lines <LINES>"),
+        ("-c", "+5", "--bytes <BYTES>"),
+        ("-n", "-+1", "--lines <LINES>"),
+    ] {
+        Command::cargo_bin(PRG)?
+            .args([flag, val, EMPTY])
+            .assert()
+            .code(2)
+            .stderr(predicate::str::contains(format!(
+                "invalid value '{val}' for '{name}': \
+                invalid digit found in string"
+            )));
+    }
+    Ok(())
+}
+
+#[test]
+fn dies_too_large() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["-n", "20E", EMPTY])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "number too large to fit in target type",
+        ));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn multiple_files_quiet() -> Result<()> {
+    run(&["-q", ONE, TWO, THREE], "tests/expected/all.q.out")?;
+    run(&["--silent", ONE, TWO, THREE], "tests/expected/all.q.out")
+}
+
+#[test]
+fn one_verbose() -> Result<()> {
+    run(&["-v", ONE], "tests/expected/one.txt.v.out")?;
+    run(&["-q", "--verbose", ONE], "tests/expected/one.txt.v.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn zero_terminated() -> Result<()> {
+    run(&["-z", "-n", "2", ZERO], "tests/expected/zero.txt.z2.out")
+}
+
+#[test]
+fn zero_terminated_all_but_last() -> Result<()> {
+    run(
+        &["--zero-terminated", "-n", "-1", ZERO],
+        "tests/expected/zero.txt.z-1.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn binary_bytes() -> Result<()> {
+    run(&["-c", "7", BINARY], "tests/expected/binary.bin.c7.out")
+}
+
+#[test]
+fn binary_bytes_stdin() -> Result<()> {
+    run_stdin(&["-c", "7"], BINARY, "tests/expected/binary.bin.c7.out")
+}
+
+#[test]
+fn binary_lines() -> Result<()> {
+    run(&["-n", "2", BINARY], "tests/expected/binary.bin.n2.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn one_chars() -> Result<()> {
+    run(&["--chars", "2", ONE], "tests/expected/one.txt.chars2.out")
+}
+
+#[test]
+fn binary_chars() -> Result<()> {
+    run(&["--chars=6", BINARY], "tests/expected/binary.bin.chars6.out")
+}
+
+#[test]
+fn dies_chars_and_bytes() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["--chars", "1", "-c", "2"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "the argument '--chars <CHARS>' cannot be used with '--bytes <BYTES>'",
+        ));
+    Ok(())
+}
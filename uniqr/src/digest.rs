@@ -0,0 +1,140 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write},
+};
+use xxhash_rust::xxh3::xxh3_128;
+
+/// 16 bytes of digest followed by 8 bytes of count
+const RECORD_LEN: u64 = 24;
+/// Rough per-entry cost of a `HashMap<u128, u64>` including its overhead
+const ENTRY_COST: u64 = 48;
+
+pub fn digest(bytes: &[u8]) -> u128 {
+    xxh3_128(bytes)
+}
+
+/// Set of line digests with an occurrence count for each of them.
+/// When a memory limit is given, entries that do not fit are moved to a
+/// sorted file of fixed size records that is searched with a binary search.
+pub struct DigestStore {
+    memory: HashMap<u128, u64>,
+    max_entries: Option<usize>,
+    spill: Option<File>,
+    spilled: u64,
+}
+
+impl DigestStore {
+    pub fn new(memory_limit: Option<u64>) -> Self {
+        DigestStore {
+            memory: HashMap::new(),
+            max_entries: memory_limit.map(|limit| (limit / ENTRY_COST).max(1) as usize),
+            spill: None,
+            spilled: 0,
+        }
+    }
+
+    /// Count one occurrence of `digest`, returning true if it was not seen before
+    pub fn insert(&mut self, digest: u128) -> io::Result<bool> {
+        if let Some(count) = self.memory.get_mut(&digest) {
+            *count += 1;
+            return Ok(false);
+        }
+        if let Some((index, count)) = self.find_spilled(digest)? {
+            self.write_count(index, count + 1)?;
+            return Ok(false);
+        }
+        self.memory.insert(digest, 1);
+        if self.max_entries.is_some_and(|max| self.memory.len() >= max) {
+            self.flush()?;
+        }
+        Ok(true)
+    }
+
+    /// Number of times `digest` was inserted
+    pub fn count(&mut self, digest: u128) -> io::Result<u64> {
+        if let Some(count) = self.memory.get(&digest) {
+            return Ok(*count);
+        }
+        Ok(self.find_spilled(digest)?.map_or(0, |(_, count)| count))
+    }
+
+    /// Merge the in-memory entries into the spill file
+    fn flush(&mut self) -> io::Result<()> {
+        let mut entries: Vec<(u128, u64)> = self.memory.drain().collect();
+        entries.sort_unstable();
+
+        let mut merged = BufWriter::new(tempfile::tempfile()?);
+        let mut written = 0;
+        let mut entries = entries.into_iter().peekable();
+        if let Some(mut old) = self.spill.take() {
+            old.seek(SeekFrom::Start(0))?;
+            let mut old = BufReader::new(old);
+            for _ in 0..self.spilled {
+                let record = read_record(&mut old)?;
+                while let Some(entry) = entries.next_if(|entry| entry.0 < record.0) {
+                    write_record(&mut merged, entry)?;
+                    written += 1;
+                }
+                write_record(&mut merged, record)?;
+                written += 1;
+            }
+        }
+        for entry in entries {
+            write_record(&mut merged, entry)?;
+            written += 1;
+        }
+
+        self.spill = Some(merged.into_inner().map_err(|e| e.into_error())?);
+        self.spilled = written;
+        Ok(())
+    }
+
+    /// Position and count of `digest` in the spill file
+    fn find_spilled(&mut self, digest: u128) -> io::Result<Option<(u64, u64)>> {
+        let (mut low, mut high) = (0, self.spilled);
+        while low < high {
+            let mid = low + (high - low) / 2;
+            let (value, count) = self.read_spilled(mid)?;
+            if value == digest {
+                return Ok(Some((mid, count)));
+            } else if value < digest {
+                low = mid + 1;
+            } else {
+                high = mid;
+            }
+        }
+        Ok(None)
+    }
+
+    fn read_spilled(&mut self, index: u64) -> io::Result<(u128, u64)> {
+        let file = self.spill_file()?;
+        file.seek(SeekFrom::Start(index * RECORD_LEN))?;
+        read_record(file)
+    }
+
+    fn write_count(&mut self, index: u64, count: u64) -> io::Result<()> {
+        let file = self.spill_file()?;
+        file.seek(SeekFrom::Start(index * RECORD_LEN + 16))?;
+        file.write_all(&count.to_le_bytes())
+    }
+
+    fn spill_file(&mut self) -> io::Result<&mut File> {
+        self.spill
+            .as_mut()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no spill file"))
+    }
+}
+
+fn read_record(input: &mut impl Read) -> io::Result<(u128, u64)> {
+    let mut digest = [0; 16];
+    let mut count = [0; 8];
+    input.read_exact(&mut digest)?;
+    input.read_exact(&mut count)?;
+    Ok((u128::from_le_bytes(digest), u64::from_le_bytes(count)))
+}
+
+fn write_record(out: &mut impl Write, (digest, count): (u128, u64)) -> io::Result<()> {
+    out.write_all(&digest.to_le_bytes())?;
+    out.write_all(&count.to_le_bytes())
+}
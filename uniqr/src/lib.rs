@@ -1,10 +1,13 @@
-use clap::Parser;
+use clap::{Parser, ValueEnum};
+use digest::{digest, DigestStore};
 use std::{
     error::Error,
     fs::File,
-    io::{self, BufRead, BufReader, Write},
+    io::{self, BufRead, BufReader, BufWriter, Seek, SeekFrom, Write},
 };
 
+mod digest;
+
 type MyResult<T> = Result<T, Box<dyn Error>>;
 
 #[derive(Debug, Parser)]
@@ -20,6 +23,51 @@ pub struct Config {
     /// Count
     #[arg(short, long, value_name = "COUNT")]
     count: bool,
+    /// Remove duplicates anywhere in the input, keeping the first occurrence
+    #[arg(short, long, visible_alias = "global")]
+    all: bool,
+    /// Memory used for --all before spilling to a temp file (K, M, G suffixes)
+    #[arg(long, value_name = "SIZE", value_parser = parse_size, requires = "all")]
+    memory_limit: Option<u64>,
+    /// Line delimiter is NUL, not newline
+    #[arg(short, long)]
+    zero_terminated: bool,
+    /// Print all duplicate lines, delimiting groups with an empty line
+    #[arg(
+        short = 'D',
+        long,
+        value_name = "METHOD",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "none",
+        conflicts_with_all = ["count", "all"]
+    )]
+    all_repeated: Option<AllRepeated>,
+    /// Print all lines, delimiting groups with an empty line
+    #[arg(
+        long,
+        value_name = "METHOD",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "separate",
+        conflicts_with_all = ["count", "all", "all_repeated"]
+    )]
+    group: Option<Group>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum AllRepeated {
+    None,
+    Prepend,
+    Separate,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Group {
+    Separate,
+    Prepend,
+    Append,
+    Both,
 }
 
 pub fn run(config: Config) -> MyResult<()> {
@@ -30,40 +78,149 @@ pub fn run(config: Config) -> MyResult<()> {
         _ => Box::new(io::stdout()),
     };
 
-    let make_output = |prev_line: &str, count: usize| -> String {
+    let delimiter = if config.zero_terminated { b'\0' } else { b'\n' };
+    if config.all {
+        return run_global(&config, file, out_file, delimiter);
+    }
+
+    let mut groups_printed = 0;
+    let mut print_group = |group: &[Vec<u8>], count: usize| -> io::Result<()> {
+        let (before, after) = match (config.all_repeated, config.group) {
+            (Some(_), _) if count < 2 => return Ok(()),
+            (Some(AllRepeated::Prepend), _) => (true, false),
+            (Some(AllRepeated::Separate), _) => (groups_printed > 0, false),
+            (_, Some(Group::Separate)) => (groups_printed > 0, false),
+            (_, Some(Group::Prepend)) => (true, false),
+            (_, Some(Group::Append)) => (false, true),
+            (_, Some(Group::Both)) => (true, false),
+            _ => (false, false),
+        };
+        if before {
+            out_file.write_all(&[delimiter])?;
+        }
         if config.count {
-            format!("{:>4} {}", count, prev_line)
-        } else {
-            prev_line.to_string()
+            write!(out_file, "{:>4} ", count)?;
+        }
+        for record in group {
+            out_file.write_all(record)?;
+            out_file.write_all(&[delimiter])?;
         }
+        if after {
+            out_file.write_all(&[delimiter])?;
+        }
+        groups_printed += 1;
+        Ok(())
     };
 
-    let mut print_output = |output: &str| write!(out_file, "{}", output);
-
-    let mut line = String::new();
-    let mut prev_line = String::new();
+    // グループの全部の行を出力するモードのときだけ、2行目以降も取っておく
+    let keep_members = config.all_repeated.is_some() || config.group.is_some();
+    let mut record = vec![];
+    let mut group: Vec<Vec<u8>> = vec![];
     let mut count = 0;
-    loop {
-        let bytes = file.read_line(&mut line)?;
-        let line_trim_end = line.trim_end();
-        if line_trim_end != prev_line.trim_end() && count > 0 {
-            let output = make_output(&prev_line, count);
-            print_output(&output)?;
+    while read_record(&mut file, delimiter, &mut record)? {
+        if count > 0 && record != group[0] {
+            print_group(&group, count)?;
+            group.clear();
             count = 0;
         }
+        if count == 0 || keep_members {
+            group.push(std::mem::take(&mut record));
+        }
         count += 1;
-        if bytes == 0 {
-            break;
+    }
+    if count > 0 {
+        print_group(&group, count)?;
+    }
+    // both は最後のグループの後ろにだけ区切りを付ける
+    if config.group == Some(Group::Both) && groups_printed > 0 {
+        out_file.write_all(&[delimiter])?;
+    }
+    Ok(())
+}
+
+fn run_global(
+    config: &Config,
+    mut file: Box<dyn BufRead>,
+    mut out_file: Box<dyn Write>,
+    delimiter: u8,
+) -> MyResult<()> {
+    let mut store = DigestStore::new(config.memory_limit);
+    // --count の合計は最後まで読まないと分からないので、初出の行を取っておく
+    let mut firsts: Vec<Vec<u8>> = vec![];
+    let mut spool = match (config.count, config.memory_limit) {
+        (true, Some(_)) => Some(BufWriter::new(tempfile::tempfile()?)),
+        _ => None,
+    };
+
+    let mut record = vec![];
+    while read_record(&mut file, delimiter, &mut record)? {
+        if !store.insert(digest(&record))? {
+            continue;
+        }
+        match (&mut spool, config.count) {
+            (Some(spool), _) => {
+                spool.write_all(&record)?;
+                spool.write_all(&[delimiter])?;
+            }
+            (None, true) => firsts.push(record.clone()),
+            (None, false) => {
+                out_file.write_all(&record)?;
+                out_file.write_all(&[delimiter])?;
+            }
+        }
+    }
+
+    let mut print_output = |record: &[u8], count: u64| -> io::Result<()> {
+        write!(out_file, "{:>4} ", count)?;
+        out_file.write_all(record)?;
+        out_file.write_all(&[delimiter])
+    };
+    if let Some(spool) = spool {
+        let mut spool = spool.into_inner().map_err(|e| e.into_error())?;
+        spool.seek(SeekFrom::Start(0))?;
+        let mut spool = BufReader::new(spool);
+        while read_record(&mut spool, delimiter, &mut record)? {
+            print_output(&record, store.count(digest(&record))?)?;
         }
-        if prev_line.trim_end() != line_trim_end {
-            prev_line.clear();
-            prev_line = line.clone();
+    } else {
+        for record in firsts {
+            print_output(&record, store.count(digest(&record))?)?;
         }
-        line.clear();
     }
     Ok(())
 }
 
+/// Read the next record into `buf` without its delimiter.
+/// A last record that lacks the delimiter is treated as if it had one.
+fn read_record(file: &mut impl BufRead, delimiter: u8, buf: &mut Vec<u8>) -> io::Result<bool> {
+    buf.clear();
+    if file.read_until(delimiter, buf)? == 0 {
+        return Ok(false);
+    }
+    if buf.last() == Some(&delimiter) {
+        buf.pop();
+    }
+    Ok(true)
+}
+
+fn parse_size(size: &str) -> Result<u64, String> {
+    let (num, unit) = match size.find(|c: char| !c.is_ascii_digit()) {
+        Some(i) => size.split_at(i),
+        None => (size, ""),
+    };
+    let unit = match unit.to_ascii_uppercase().as_str() {
+        "" => 1,
+        "K" => 1 << 10,
+        "M" => 1 << 20,
+        "G" => 1 << 30,
+        _ => return Err(format!("invalid size \"{}\"", size)),
+    };
+    num.parse::<u64>()
+        .ok()
+        .and_then(|n| n.checked_mul(unit))
+        .ok_or_else(|| format!("invalid size \"{}\"", size))
+}
+
 fn open(filename: &str) -> MyResult<Box<dyn BufRead>> {
     match filename {
         "-" => Ok(Box::new(BufReader::new(io::stdin()))),
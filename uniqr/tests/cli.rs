@@ -617,3 +617,168 @@ fn t6_outfile_count() -> Result<()> {
 fn t6_stdin_outfile_count() -> Result<()> {
     run_stdin_outfile_count(&T6)
 }
+
+// --------------------------------------------------
+fn run_args(args: &[&str], expected_file: &str) -> Result<()> {
+    let expected = fs::read(expected_file)?;
+    let output = Command::cargo_bin(PRG)?.args(args).output().expect("fail");
+    assert!(output.status.success());
+    assert_eq!(output.stdout, expected);
+    Ok(())
+}
+
+#[test]
+fn three_all() -> Result<()> {
+    run_args(&[THREE.input, "--all"], "tests/expected/three.txt.a.out")
+}
+
+#[test]
+fn three_global_count() -> Result<()> {
+    run_args(
+        &[THREE.input, "--global", "-c"],
+        "tests/expected/three.txt.a.c.out",
+    )
+}
+
+#[test]
+fn three_all_count_memory_limit() -> Result<()> {
+    run_args(
+        &[THREE.input, "-a", "-c", "--memory-limit", "1"],
+        "tests/expected/three.txt.a.c.out",
+    )
+}
+
+#[test]
+fn t4_all_memory_limit() -> Result<()> {
+    run_args(
+        &[T4.input, "-a", "--memory-limit", "1K"],
+        "tests/expected/t4.txt.a.out",
+    )
+}
+
+#[test]
+fn dies_memory_limit_without_all() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args([THREE.input, "--memory-limit", "1K"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--all"));
+    Ok(())
+}
+
+#[test]
+fn dies_bad_memory_limit() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args([THREE.input, "-a", "--memory-limit", "lots"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(r#"invalid size "lots""#));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn spaces() -> Result<()> {
+    run_args(
+        &["tests/inputs/spaces.txt"],
+        "tests/expected/spaces.txt.out",
+    )
+}
+
+#[test]
+fn spaces_count() -> Result<()> {
+    run_args(
+        &["tests/inputs/spaces.txt", "-c"],
+        "tests/expected/spaces.txt.c.out",
+    )
+}
+
+#[test]
+fn invalid_utf8_count() -> Result<()> {
+    run_args(
+        &["tests/inputs/invalid.txt", "-c"],
+        "tests/expected/invalid.txt.c.out",
+    )
+}
+
+#[test]
+fn zero_terminated_count() -> Result<()> {
+    run_args(
+        &["tests/inputs/zero.txt", "-z", "-c"],
+        "tests/expected/zero.txt.z.c.out",
+    )
+}
+
+#[test]
+fn zero_terminated_all() -> Result<()> {
+    run_args(
+        &["tests/inputs/zero.txt", "--zero-terminated", "--all"],
+        "tests/expected/zero.txt.z.a.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn three_all_repeated() -> Result<()> {
+    run_args(&[THREE.input, "-D"], "tests/expected/three.txt.D.out")
+}
+
+#[test]
+fn three_all_repeated_prepend() -> Result<()> {
+    run_args(
+        &[THREE.input, "--all-repeated=prepend"],
+        "tests/expected/three.txt.D.prepend.out",
+    )
+}
+
+#[test]
+fn three_all_repeated_separate() -> Result<()> {
+    run_args(
+        &[THREE.input, "--all-repeated=separate"],
+        "tests/expected/three.txt.D.separate.out",
+    )
+}
+
+#[test]
+fn three_group() -> Result<()> {
+    run_args(
+        &[THREE.input, "--group"],
+        "tests/expected/three.txt.group.out",
+    )
+}
+
+#[test]
+fn three_group_append() -> Result<()> {
+    run_args(
+        &[THREE.input, "--group=append"],
+        "tests/expected/three.txt.group.append.out",
+    )
+}
+
+#[test]
+fn three_group_both() -> Result<()> {
+    run_args(
+        &[THREE.input, "--group=both"],
+        "tests/expected/three.txt.group.both.out",
+    )
+}
+
+#[test]
+fn dies_group_with_count() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args([THREE.input, "--group", "-c"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+    Ok(())
+}
+
+#[test]
+fn dies_bad_group_method() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args([THREE.input, "--group=middle"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("invalid value 'middle'"));
+    Ok(())
+}
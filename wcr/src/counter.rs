@@ -0,0 +1,195 @@
+/// Streaming counter over chunks of bytes.
+/// Words, characters and line widths are tracked across chunk borders, so a
+/// multi-byte character or a word may be split between two chunks.
+#[derive(Debug, Default)]
+pub struct Counter {
+    pub lines: usize,
+    pub words: usize,
+    pub bytes: usize,
+    pub chars: usize,
+    pub max_line_length: usize,
+    in_word: bool,
+    line_pos: usize,
+    /// Start of a multi-byte character cut off at the end of the last chunk
+    pending: Vec<u8>,
+}
+
+enum Decoded {
+    Char(char, usize),
+    Invalid,
+    Incomplete,
+}
+
+impl Counter {
+    pub fn update(&mut self, chunk: &[u8]) {
+        self.bytes += chunk.len();
+        let mut i = 0;
+
+        if !self.pending.is_empty() {
+            let take = (4 - self.pending.len()).min(chunk.len());
+            let mut bytes = self.pending.clone();
+            bytes.extend_from_slice(&chunk[..take]);
+            match decode(&bytes) {
+                Decoded::Char(c, len) => {
+                    self.char(c);
+                    i = len - self.pending.len();
+                    self.pending.clear();
+                }
+                Decoded::Incomplete => {
+                    self.pending = bytes;
+                    return;
+                }
+                Decoded::Invalid => {
+                    // 持ち越したバイトはどれも文字にならない
+                    for _ in 0..self.pending.len() {
+                        self.invalid();
+                    }
+                    self.pending.clear();
+                }
+            }
+        }
+
+        while i < chunk.len() {
+            let byte = chunk[i];
+            if byte.is_ascii() {
+                self.char(byte as char);
+                i += 1;
+                continue;
+            }
+            match decode(&chunk[i..]) {
+                Decoded::Char(c, len) => {
+                    self.char(c);
+                    i += len;
+                }
+                Decoded::Invalid => {
+                    self.invalid();
+                    i += 1;
+                }
+                Decoded::Incomplete => {
+                    self.pending.extend_from_slice(&chunk[i..]);
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Account for the bytes of an unfinished character at the end of input
+    pub fn finish(&mut self) {
+        for _ in 0..self.pending.len() {
+            self.invalid();
+        }
+        self.pending.clear();
+        self.end_line();
+    }
+
+    fn char(&mut self, c: char) {
+        self.chars += 1;
+        match c {
+            '\n' => {
+                self.lines += 1;
+                self.end_line();
+            }
+            '\r' | '\x0c' => self.end_line(),
+            '\t' => self.line_pos += 8 - self.line_pos % 8,
+            c if !c.is_control() => self.line_pos += 1,
+            _ => {}
+        }
+        if c.is_whitespace() {
+            self.in_word = false;
+        } else if !self.in_word {
+            self.in_word = true;
+            self.words += 1;
+        }
+    }
+
+    /// A byte that is not part of valid UTF-8 is not a character, but it
+    /// still belongs to a word
+    fn invalid(&mut self) {
+        if !self.in_word {
+            self.in_word = true;
+            self.words += 1;
+        }
+    }
+
+    fn end_line(&mut self) {
+        self.max_line_length = self.max_line_length.max(self.line_pos);
+        self.line_pos = 0;
+    }
+}
+
+fn decode(bytes: &[u8]) -> Decoded {
+    let len = match bytes[0] {
+        0x00..=0x7f => 1,
+        0xc2..=0xdf => 2,
+        0xe0..=0xef => 3,
+        0xf0..=0xf4 => 4,
+        _ => return Decoded::Invalid,
+    };
+    if bytes.len() < len {
+        return if bytes[1..].iter().all(|b| b & 0xc0 == 0x80) {
+            Decoded::Incomplete
+        } else {
+            Decoded::Invalid
+        };
+    }
+    match std::str::from_utf8(&bytes[..len]) {
+        Ok(s) => Decoded::Char(s.chars().next().unwrap(), len),
+        Err(_) => Decoded::Invalid,
+    }
+}
+
+/// Number of newlines in `chunk`
+pub fn count_newlines(chunk: &[u8]) -> usize {
+    memchr::memchr_iter(b'\n', chunk).count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Counter;
+
+    fn count_chunks(chunks: &[&[u8]]) -> Counter {
+        let mut counter = Counter::default();
+        for chunk in chunks {
+            counter.update(chunk);
+        }
+        counter.finish();
+        counter
+    }
+
+    #[test]
+    fn test_split_word() {
+        let counter = count_chunks(&[b"hel", b"lo wor", b"ld\n"]);
+        assert_eq!(counter.words, 2);
+        assert_eq!(counter.lines, 1);
+        assert_eq!(counter.bytes, 12);
+    }
+
+    #[test]
+    fn test_split_char() {
+        // "あ い" with both characters cut between chunks
+        let counter = count_chunks(&[b"\xe3", b"\x81", b"\x82 \xe3\x81", b"\x84"]);
+        assert_eq!(counter.chars, 3);
+        assert_eq!(counter.words, 2);
+        assert_eq!(counter.bytes, 7);
+    }
+
+    #[test]
+    fn test_split_invalid() {
+        // A lead byte followed by ASCII in the next chunk is invalid on its own
+        let counter = count_chunks(&[b"a\xe3", b"b c"]);
+        assert_eq!(counter.chars, 4);
+        assert_eq!(counter.words, 2);
+
+        // An unfinished character at the end of input
+        let counter = count_chunks(&[b"a \xe3\x81"]);
+        assert_eq!(counter.chars, 2);
+        assert_eq!(counter.words, 2);
+    }
+
+    #[test]
+    fn test_unicode_whitespace() {
+        let counter = count_chunks(&["a\u{3000}b\u{a0}c".as_bytes()]);
+        assert_eq!(counter.words, 3);
+        assert_eq!(counter.chars, 5);
+    }
+}
@@ -1,182 +1,513 @@
-use clap::{App, Arg};
+use clap::{Parser, ValueEnum};
+use counter::{count_newlines, Counter};
+use segment::Segmenter;
+use serde_json::{json, Value};
 use std::error::Error;
-use std::fs::File;
-use std::io::{self, BufRead, BufReader};
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Read};
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+mod counter;
+mod segment;
 
 type MyResult<T> = Result<T, Box<dyn Error>>;
 
-#[derive(Debug)]
+const BUF_SIZE: usize = 64 * 1024;
+
+#[derive(Debug, Parser)]
+#[command(author, version, about)]
+/// Rust wc
 pub struct Config {
+    /// Input file(s)
+    #[arg(value_name = "FILE", default_value = "-")]
     files: Vec<String>,
+
+    /// Count lines
+    #[arg(short, long)]
     lines: bool,
-    words: bool,
+
+    /// Count words, separated by whitespace or by Unicode word boundaries
+    #[arg(
+        short,
+        long,
+        value_name = "MODE",
+        value_enum,
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "whitespace"
+    )]
+    words: Option<Words>,
+
+    /// Count bytes
+    #[arg(short = 'c', long)]
     bytes: bool,
+
+    /// Count characters
+    #[arg(short = 'm', long, conflicts_with = "bytes")]
     chars: bool,
+
+    /// Count grapheme clusters (user-perceived characters)
+    #[arg(long)]
+    graphemes: bool,
+
+    /// Print the length of the longest line
+    #[arg(short = 'L', long)]
+    max_line_length: bool,
+
+    /// Print the width of the longest line in terminal columns
+    #[arg(long)]
+    display_width: bool,
+
+    /// Read input file names separated by NUL from F ("-" for STDIN)
+    #[arg(long, value_name = "F", conflicts_with = "files")]
+    files0_from: Option<String>,
+
+    /// When to print a line with total counts
+    #[arg(long, value_name = "WHEN", value_enum, default_value_t = Total::Auto)]
+    total: Total,
+
+    /// Output format: json writes one object per line, csv writes a header
+    /// and the total as a row with an empty file name
+    #[arg(long, value_enum, default_value_t = Format::Text)]
+    format: Format,
 }
 
-pub fn get_args() -> MyResult<Config> {
-    let matches = App::new("wcr")
-        .version("0.1.0")
-        .author("teppei")
-        .about("Rust wc")
-        .arg(
-            Arg::with_name("files")
-                .value_name("FILE")
-                .help("Input file(s)")
-                .multiple(true)
-                .default_value("-"),
-        )
-        .arg(
-            Arg::with_name("lines")
-                .value_name("LINE")
-                .help("Count lines")
-                .short("l")
-                .long("lines")
-                .takes_value(false),
-        )
-        .arg(
-            Arg::with_name("words")
-                .value_name("WORD")
-                .help("Count words")
-                .short("w")
-                .long("words")
-                .takes_value(false),
-        )
-        .arg(
-            Arg::with_name("bytes")
-                .value_name("BYTE")
-                .help("Count bytes")
-                .short("c")
-                .long("bytes")
-                .takes_value(false),
-        )
-        .arg(
-            Arg::with_name("chars")
-                .value_name("CHAR")
-                .help("Count characters")
-                .short("m")
-                .long("chars")
-                .conflicts_with("bytes")
-                .takes_value(false),
-        )
-        .get_matches();
-
-    let mut lines = matches.is_present("lines");
-    let mut words = matches.is_present("words");
-    let mut bytes = matches.is_present("bytes");
-    let chars = matches.is_present("chars");
-
-    if [lines, words, bytes, chars].iter().all(|&v| !v) {
-        lines = true;
-        words = true;
-        bytes = true;
-    }
-
-    Ok(Config {
-        files: matches.values_of_lossy("files").unwrap(),
-        lines,
-        words,
-        bytes,
-        chars,
-    })
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Words {
+    /// Runs of non-whitespace characters
+    Whitespace,
+    /// Words as found by Unicode word segmentation (UAX #29)
+    Unicode,
 }
 
-pub fn run(config: Config) -> MyResult<()> {
-    let mut total_lines = 0;
-    let mut total_words = 0;
-    let mut total_bytes = 0;
-    let mut total_chars = 0;
-    for filename in &config.files {
-        match open(filename) {
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Format {
+    Text,
+    Json,
+    Csv,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Total {
+    Auto,
+    Always,
+    Only,
+    Never,
+}
+
+impl Config {
+    /// Which counts are asked for, in the order they are printed
+    fn selected(&self) -> [bool; 7] {
+        [
+            self.lines,
+            self.words.is_some(),
+            self.bytes,
+            self.chars,
+            self.graphemes,
+            self.max_line_length,
+            self.display_width,
+        ]
+    }
+}
+
+pub fn run(mut config: Config) -> MyResult<()> {
+    if config.selected().iter().all(|&v| !v) {
+        config.lines = true;
+        config.words = Some(Words::Whitespace);
+        config.bytes = true;
+    }
+
+    let files = match &config.files0_from {
+        Some(list) => read_files0(list)?,
+        None => config.files.clone(),
+    };
+
+    let results = count_files(&config, &files);
+    let mut total = FileInfo::default();
+    for file_info in results.iter().flatten() {
+        total.num_lines += file_info.num_lines;
+        total.num_words += file_info.num_words;
+        total.num_bytes += file_info.num_bytes;
+        total.num_chars += file_info.num_chars;
+        total.num_graphemes += file_info.num_graphemes;
+        total.max_line_length = total.max_line_length.max(file_info.max_line_length);
+        total.display_width = total.display_width.max(file_info.display_width);
+    }
+    if config.format != Format::Text {
+        return print_records(&config, &files, &results, &total);
+    }
+
+    let width = number_width(&config, &files, &results, &total);
+    for (filename, result) in files.iter().zip(&results) {
+        match result {
             Err(err) => eprintln!("{}: {}", filename, err),
-            Ok(file) => {
-                let file_info = count(file)?;
-                total_lines += file_info.num_lines;
-                total_words += file_info.num_words;
-                total_bytes += file_info.num_bytes;
-                total_chars += file_info.num_chars;
-                print_counts(&config, file_info, filename);
+            Ok(file_info) => {
+                if config.total != Total::Only {
+                    print_counts(&config, file_info, Some(filename), width);
+                }
             }
         }
     }
-    if config.files.len() > 1 {
-        let total_info = FileInfo {
-            num_lines: total_lines,
-            num_words: total_words,
-            num_bytes: total_bytes,
-            num_chars: total_chars,
-        };
-        print_counts(&config, total_info, "total");
+
+    match config.total {
+        Total::Auto if files.len() > 1 => print_counts(&config, &total, Some("total"), width),
+        Total::Always => print_counts(&config, &total, Some("total"), width),
+        Total::Only => print_counts(&config, &total, None, width),
+        _ => {}
     }
     Ok(())
 }
 
+/// Count `files` on a bounded number of threads, returning the results in
+/// the same order as `files`
+fn count_files(config: &Config, files: &[String]) -> Vec<Result<FileInfo, String>> {
+    let jobs = thread::available_parallelism()
+        .map_or(1, NonZeroUsize::get)
+        .min(files.len());
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new(files.iter().map(|_| None).collect::<Vec<_>>());
+
+    thread::scope(|scope| {
+        for _ in 0..jobs {
+            scope.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                let Some(filename) = files.get(index) else {
+                    break;
+                };
+                let result = count_file(config, filename).map_err(|e| e.to_string());
+                results.lock().unwrap()[index] = Some(result);
+            });
+        }
+    });
+    results
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .map(Option::unwrap)
+        .collect()
+}
+
+/// Column width in the manner of GNU wc: wide enough for the total size of
+/// the regular input files (at least 7 when other inputs are involved), and
+/// 1 when there is only a single number to print
+fn number_width(
+    config: &Config,
+    files: &[String],
+    results: &[Result<FileInfo, String>],
+    total: &FileInfo,
+) -> usize {
+    let num_counts = config.selected().iter().filter(|&&v| v).count();
+    if config.total == Total::Only || (files.len() == 1 && num_counts == 1) {
+        return 1;
+    }
+
+    let mut minimum = 1;
+    let mut regular_total = 0;
+    for (filename, result) in files.iter().zip(results) {
+        if result.is_err() {
+            continue;
+        }
+        let path = if filename == "-" {
+            "/dev/stdin"
+        } else {
+            filename
+        };
+        match fs::metadata(path) {
+            Ok(metadata) if metadata.is_file() => regular_total += metadata.len(),
+            _ => minimum = 7,
+        }
+    }
+    // 数え終わっているので、実際の合計が収まる幅も確保する
+    let largest = [
+        total.num_lines,
+        total.num_words,
+        total.num_bytes,
+        total.num_chars,
+        total.num_graphemes,
+        total.max_line_length,
+        total.display_width,
+    ]
+    .into_iter()
+    .max()
+    .unwrap_or(0);
+    digits(regular_total as usize)
+        .max(digits(largest))
+        .max(minimum)
+}
+
+fn digits(mut value: usize) -> usize {
+    let mut width = 1;
+    while value >= 10 {
+        value /= 10;
+        width += 1;
+    }
+    width
+}
+
+/// File names separated by NUL in `list`
+fn read_files0(list: &str) -> MyResult<Vec<String>> {
+    let mut names = vec![];
+    open(list)
+        .map_err(|e| format!("cannot open '{}' for reading: {}", list, e))?
+        .read_to_end(&mut names)?;
+    if names.last() == Some(&0) {
+        names.pop();
+    }
+    if names.is_empty() {
+        return Ok(vec![]);
+    }
+    Ok(names
+        .split(|&b| b == 0)
+        .enumerate()
+        .filter_map(|(i, name)| {
+            if name.is_empty() {
+                eprintln!("{}:{}: invalid zero-length file name", list, i + 1);
+                None
+            } else {
+                Some(String::from_utf8_lossy(name).into_owned())
+            }
+        })
+        .collect())
+}
+
+/// Count `filename`, reading only as much as the selected counts need
+fn count_file(config: &Config, filename: &str) -> MyResult<FileInfo> {
+    if config.graphemes || config.display_width || config.words == Some(Words::Unicode) {
+        return count_segments(open(filename)?, config.words == Some(Words::Unicode));
+    }
+    if !(config.words.is_some() || config.chars || config.max_line_length) {
+        if !config.lines && filename != "-" {
+            // -c だけなら通常ファイルはサイズを見るだけで良い
+            let metadata = fs::metadata(filename)?;
+            if metadata.is_file() && metadata.len() > 0 {
+                return Ok(FileInfo {
+                    num_bytes: metadata.len() as usize,
+                    ..Default::default()
+                });
+            }
+        }
+        return count_lines(open(filename)?);
+    }
+    count(open(filename)?)
+}
+
 fn open(filename: &str) -> MyResult<Box<dyn BufRead>> {
     match filename {
-        "-" => Ok(Box::new(BufReader::new(io::stdin()))),
-        _ => Ok(Box::new(BufReader::new(File::open(filename)?))),
+        "-" => Ok(Box::new(BufReader::with_capacity(BUF_SIZE, io::stdin()))),
+        _ => Ok(Box::new(BufReader::with_capacity(
+            BUF_SIZE,
+            File::open(filename)?,
+        ))),
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Default, PartialEq)]
 pub struct FileInfo {
     num_lines: usize,
     num_words: usize,
     num_bytes: usize,
     num_chars: usize,
+    num_graphemes: usize,
+    max_line_length: usize,
+    display_width: usize,
 }
 
 pub fn count(mut file: impl BufRead) -> MyResult<FileInfo> {
-    let mut num_lines = 0;
-    let mut num_words = 0;
-    let mut num_bytes = 0;
-    let mut num_chars = 0;
-    let mut line = String::new();
-
+    let mut counter = Counter::default();
     loop {
-        let line_bytes = file.read_line(&mut line)?;
-        if line_bytes == 0 {
+        let chunk = file.fill_buf()?;
+        if chunk.is_empty() {
             break;
         }
-        num_lines += 1;
-        num_words += line.split_whitespace().count();
-        num_bytes += line_bytes;
-        num_chars += line.chars().count();
+        counter.update(chunk);
+        let len = chunk.len();
+        file.consume(len);
+    }
+    counter.finish();
+
+    Ok(FileInfo {
+        num_lines: counter.lines,
+        num_words: counter.words,
+        num_bytes: counter.bytes,
+        num_chars: counter.chars,
+        max_line_length: counter.max_line_length,
+        ..Default::default()
+    })
+}
+
+/// Count line by line, adding the counts that need Unicode segmentation.
+/// With `unicode_words`, words are counted by UAX #29 instead of whitespace.
+pub fn count_segments(mut file: impl BufRead, unicode_words: bool) -> MyResult<FileInfo> {
+    let mut counter = Counter::default();
+    let mut segmenter = Segmenter::default();
+    let mut line = Vec::new();
+    while file.read_until(b'\n', &mut line)? > 0 {
+        counter.update(&line);
+        segmenter.update(&line);
         line.clear();
     }
+    counter.finish();
 
     Ok(FileInfo {
-        num_lines,
-        num_words,
-        num_bytes,
-        num_chars,
+        num_lines: counter.lines,
+        num_words: if unicode_words {
+            segmenter.words
+        } else {
+            counter.words
+        },
+        num_bytes: counter.bytes,
+        num_chars: counter.chars,
+        num_graphemes: segmenter.graphemes,
+        max_line_length: counter.max_line_length,
+        display_width: segmenter.display_width,
     })
 }
 
-fn print_counts(config: &Config, file_info: FileInfo, filename: &str) {
-    let _lines = format_field(file_info.num_lines, config.lines);
-    let _words = format_field(file_info.num_words, config.words);
-    let _bytes = format_field(file_info.num_bytes, config.bytes);
-    let _chars = format_field(file_info.num_chars, config.chars);
-    let _file = if filename != "-" {
-        format!(" {}", filename)
-    } else {
-        String::new()
+/// Count only lines and bytes, which needs no decoding
+fn count_lines(mut file: impl BufRead) -> MyResult<FileInfo> {
+    let mut info = FileInfo::default();
+    loop {
+        let chunk = file.fill_buf()?;
+        if chunk.is_empty() {
+            break;
+        }
+        info.num_lines += count_newlines(chunk);
+        info.num_bytes += chunk.len();
+        let len = chunk.len();
+        file.consume(len);
+    }
+    Ok(info)
+}
+
+/// Write the counts of each file, the files that failed and the total as
+/// json or csv records
+fn print_records(
+    config: &Config,
+    files: &[String],
+    results: &[Result<FileInfo, String>],
+    total: &FileInfo,
+) -> MyResult<()> {
+    let names: Vec<&str> = named_counts(config, total)
+        .into_iter()
+        .map(|(name, _)| name)
+        .collect();
+    let mut csv_out = csv::Writer::from_writer(io::stdout());
+    if config.format == Format::Csv {
+        let mut header = vec!["file"];
+        header.extend(&names);
+        header.push("error");
+        csv_out.write_record(header)?;
+    }
+
+    for (filename, result) in files.iter().zip(results) {
+        if let Err(err) = result {
+            eprintln!("{}: {}", filename, err);
+        }
+        if config.total == Total::Only {
+            continue;
+        }
+        match (config.format, result) {
+            (Format::Json, Ok(file_info)) => println!(
+                "{}",
+                json!({ "file": filename, "counts": counts_object(config, file_info) })
+            ),
+            (Format::Json, Err(err)) => println!("{}", json!({ "file": filename, "error": err })),
+            (_, Ok(file_info)) => {
+                let mut record = vec![filename.to_string()];
+                record.extend(count_values(config, file_info));
+                record.push(String::new());
+                csv_out.write_record(record)?;
+            }
+            (_, Err(err)) => {
+                let mut record = vec![filename.to_string()];
+                record.extend(names.iter().map(|_| String::new()));
+                record.push(err.to_string());
+                csv_out.write_record(record)?;
+            }
+        }
+    }
+
+    let show_total = match config.total {
+        Total::Auto => files.len() > 1,
+        Total::Always | Total::Only => true,
+        Total::Never => false,
     };
-    println!("{}{}{}{}{}", _lines, _words, _bytes, _chars, _file);
+    if show_total {
+        match config.format {
+            Format::Json => println!("{}", json!({ "total": counts_object(config, total) })),
+            _ => {
+                let mut record = vec![String::new()];
+                record.extend(count_values(config, total));
+                record.push(String::new());
+                csv_out.write_record(record)?;
+            }
+        }
+    }
+    csv_out.flush()?;
+    Ok(())
 }
 
-fn format_field(value: usize, show: bool) -> String {
-    if show {
-        format!("{:8}", value)
-    } else {
-        String::new()
+/// The selected counts of `file_info` with their field names
+fn named_counts(config: &Config, file_info: &FileInfo) -> Vec<(&'static str, usize)> {
+    let counts = [
+        ("lines", file_info.num_lines),
+        ("words", file_info.num_words),
+        ("bytes", file_info.num_bytes),
+        ("chars", file_info.num_chars),
+        ("graphemes", file_info.num_graphemes),
+        ("max_line_length", file_info.max_line_length),
+        ("display_width", file_info.display_width),
+    ];
+    counts
+        .into_iter()
+        .zip(config.selected())
+        .filter_map(|(count, show)| show.then_some(count))
+        .collect()
+}
+
+fn count_values(config: &Config, file_info: &FileInfo) -> Vec<String> {
+    named_counts(config, file_info)
+        .into_iter()
+        .map(|(_, value)| value.to_string())
+        .collect()
+}
+
+fn counts_object(config: &Config, file_info: &FileInfo) -> Value {
+    Value::Object(
+        named_counts(config, file_info)
+            .into_iter()
+            .map(|(name, value)| (name.to_string(), value.into()))
+            .collect(),
+    )
+}
+
+fn print_counts(config: &Config, file_info: &FileInfo, filename: Option<&str>, width: usize) {
+    let fields = [
+        format_field(file_info.num_lines, config.lines, width),
+        format_field(file_info.num_words, config.words.is_some(), width),
+        format_field(file_info.num_bytes, config.bytes, width),
+        format_field(file_info.num_chars, config.chars, width),
+        format_field(file_info.num_graphemes, config.graphemes, width),
+        format_field(file_info.max_line_length, config.max_line_length, width),
+        format_field(file_info.display_width, config.display_width, width),
+    ];
+    let mut line: Vec<String> = fields.into_iter().flatten().collect();
+    if let Some(filename) = filename.filter(|&filename| filename != "-") {
+        line.push(filename.to_string());
     }
+    println!("{}", line.join(" "));
+}
+
+fn format_field(value: usize, show: bool, width: usize) -> Option<String> {
+    show.then(|| format!("{:width$}", value))
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{count, format_field, FileInfo};
+    use super::{count, count_lines, count_segments, format_field, FileInfo};
     use std::io::Cursor;
 
     #[test]
@@ -189,14 +520,70 @@ mod tests {
             num_words: 10,
             num_bytes: 47,
             num_chars: 47,
+            max_line_length: 45,
+            ..Default::default()
         };
         assert_eq!(info.unwrap(), expected);
     }
 
+    #[test]
+    fn test_count_segments() {
+        let text = "日本語の文章\r\nab c\n";
+        let info = count_segments(Cursor::new(text), true).unwrap();
+        let expected = FileInfo {
+            num_lines: 2,
+            num_words: 8,
+            num_bytes: 25,
+            num_chars: 13,
+            num_graphemes: 12,
+            max_line_length: 6,
+            display_width: 12,
+        };
+        assert_eq!(info, expected);
+        let info = count_segments(Cursor::new(text), false).unwrap();
+        assert_eq!(info.num_words, 3);
+    }
+
+    #[test]
+    fn test_count_max_line_length() {
+        let max = |text: &str| count(Cursor::new(text)).unwrap().max_line_length;
+        assert_eq!(max("abc\n"), 3);
+        assert_eq!(max("abc\r\n"), 3);
+        assert_eq!(max("a\tb"), 9);
+        assert_eq!(max("\t\t\nabc\n"), 16);
+        assert_eq!(max(""), 0);
+    }
+
+    #[test]
+    fn test_count_invalid_utf8() {
+        let info = count(Cursor::new(b"caf\xc3\xa9 \xff\xfe x\n")).unwrap();
+        let expected = FileInfo {
+            num_lines: 1,
+            num_words: 3,
+            num_bytes: 11,
+            num_chars: 8,
+            max_line_length: 7,
+            ..Default::default()
+        };
+        assert_eq!(info, expected);
+    }
+
+    #[test]
+    fn test_count_lines() {
+        let info = count_lines(Cursor::new("a b\nc\n\nd")).unwrap();
+        let expected = FileInfo {
+            num_lines: 3,
+            num_bytes: 8,
+            ..Default::default()
+        };
+        assert_eq!(info, expected);
+    }
+
     #[test]
     fn test_format_field() {
-        assert_eq!(format_field(1, true), "       1");
-        assert_eq!(format_field(1, false), "");
-        assert_eq!(format_field(10, true), "      10");
+        assert_eq!(format_field(1, true, 8), Some("       1".to_string()));
+        assert_eq!(format_field(1, false, 8), None);
+        assert_eq!(format_field(10, true, 3), Some(" 10".to_string()));
+        assert_eq!(format_field(1000, true, 3), Some("1000".to_string()));
     }
 }
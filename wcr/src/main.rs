@@ -1,6 +1,11 @@
+use clap::Parser;
+use wcr::Config;
+
 fn main() {
-  if let Err(e) = wcr::get_args().and_then(wcr::run) {
-    eprintln!("{}", e);
-    std::process::exit(1);
-  }
+    let config = Config::parse();
+
+    if let Err(e) = wcr::run(config) {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
 }
@@ -0,0 +1,75 @@
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+/// Counts that need Unicode text segmentation.
+/// Input is given one line at a time: both word and grapheme boundaries
+/// always fall on a line feed, so nothing is lost between lines.
+/// Invalid UTF-8 is replaced with U+FFFD before segmenting.
+#[derive(Debug, Default)]
+pub struct Segmenter {
+    /// Words in the sense of UAX #29, i.e. segments with a letter or digit
+    pub words: usize,
+    pub graphemes: usize,
+    /// Widest line in terminal columns
+    pub display_width: usize,
+}
+
+impl Segmenter {
+    pub fn update(&mut self, line: &[u8]) {
+        let line = String::from_utf8_lossy(line);
+        self.words += line.unicode_words().count();
+
+        let mut width = 0;
+        for grapheme in line.graphemes(true) {
+            self.graphemes += 1;
+            match grapheme {
+                "\n" | "\r\n" | "\r" | "\x0c" => {
+                    self.display_width = self.display_width.max(width);
+                    width = 0;
+                }
+                "\t" => width += 8 - width % 8,
+                g if g.starts_with(char::is_control) => {}
+                g => width += g.width(),
+            }
+        }
+        self.display_width = self.display_width.max(width);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Segmenter;
+
+    fn segment(lines: &[&str]) -> Segmenter {
+        let mut segmenter = Segmenter::default();
+        for line in lines {
+            segmenter.update(line.as_bytes());
+        }
+        segmenter
+    }
+
+    #[test]
+    fn test_words() {
+        assert_eq!(
+            segment(&["The quick (\"brown\") fox can't jump 32.3 feet.\n"]).words,
+            8
+        );
+        // 漢字とかなは一文字ずつ区切られる
+        assert_eq!(segment(&["今日は晴れ\n"]).words, 5);
+        assert_eq!(segment(&["ｶﾀｶﾅ カタカナ\n"]).words, 2);
+    }
+
+    #[test]
+    fn test_graphemes() {
+        let segmenter = segment(&["e\u{301}\u{1f468}\u{200d}\u{1f469}\u{200d}\u{1f467}\r\n"]);
+        assert_eq!(segmenter.graphemes, 3);
+    }
+
+    #[test]
+    fn test_display_width() {
+        assert_eq!(segment(&["日本語\n", "abcd\n"]).display_width, 6);
+        assert_eq!(segment(&["a\tb\n"]).display_width, 9);
+        assert_eq!(segment(&["e\u{301}\u{1f600}"]).display_width, 3);
+        assert_eq!(segment(&["abc\rde\n"]).display_width, 3);
+    }
+}
@@ -9,6 +9,7 @@ const PRG: &str = "wcr";
 const EMPTY: &str = "tests/inputs/empty.txt";
 const FOX: &str = "tests/inputs/fox.txt";
 const ATLAMAL: &str = "tests/inputs/atlamal.txt";
+const NIHONGO: &str = "tests/inputs/nihongo.txt";
 
 // --------------------------------------------------
 fn gen_bad_file() -> String {
@@ -29,11 +30,11 @@ fn gen_bad_file() -> String {
 #[test]
 fn dies_chars_and_bytes() -> TestResult {
     Command::cargo_bin(PRG)?
-        .args(&["-m", "-c"])
+        .args(["-m", "-c"])
         .assert()
         .failure()
         .stderr(predicate::str::contains(
-            "The argument '--bytes' cannot be used with '--chars'",
+            "the argument '--chars' cannot be used with '--bytes'",
         ));
     Ok(())
 }
@@ -212,3 +213,237 @@ fn test_all_words_lines() -> TestResult {
 fn test_all_bytes_lines() -> TestResult {
     run(&["-cl", EMPTY, FOX, ATLAMAL], "tests/expected/all.cl.out")
 }
+
+// --------------------------------------------------
+#[test]
+fn test_all_max_line_length() -> TestResult {
+    run(&["-L", EMPTY, FOX, ATLAMAL], "tests/expected/all.L.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn test_all_total_only() -> TestResult {
+    run(
+        &["--total=only", EMPTY, FOX, ATLAMAL],
+        "tests/expected/all.total_only.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn test_all_total_never() -> TestResult {
+    run(
+        &["--total", "never", EMPTY, FOX, ATLAMAL],
+        "tests/expected/all.total_never.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn fox_total_always() -> TestResult {
+    run(
+        &["--total=always", FOX],
+        "tests/expected/fox.txt.total_always.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn test_all_files0_from() -> TestResult {
+    run(
+        &["--files0-from", "tests/inputs/files0.txt"],
+        "tests/expected/all.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn test_all_files0_from_stdin() -> TestResult {
+    let input = fs::read("tests/inputs/files0.txt")?;
+    let expected = fs::read_to_string("tests/expected/all.out")?;
+    Command::cargo_bin(PRG)?
+        .arg("--files0-from=-")
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stdout(expected);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn files0_from_zero_length_name() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .arg("--files0-from=-")
+        .write_stdin(format!("{}\0\0", FOX))
+        .assert()
+        .success()
+        .stdout(" 1  9 48 tests/inputs/fox.txt\n")
+        .stderr("-:2: invalid zero-length file name\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_files0_from_with_files() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--files0-from", "tests/inputs/files0.txt", FOX])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn many_files_in_order() -> TestResult {
+    let files: Vec<&str> = [ATLAMAL, FOX, EMPTY]
+        .iter()
+        .cycle()
+        .take(30)
+        .copied()
+        .collect();
+    run(&files, "tests/expected/many.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn width_from_stdin() -> TestResult {
+    // The size of a pipe is unknown, so the columns are at least 7 wide
+    Command::cargo_bin(PRG)?
+        .args(["-l", "-", FOX])
+        .write_stdin("a\n")
+        .assert()
+        .success()
+        .stdout("      1\n      1 tests/inputs/fox.txt\n      2 total\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn nihongo_words_unicode() -> TestResult {
+    run(
+        &["--words=unicode", NIHONGO],
+        "tests/expected/nihongo.txt.words_unicode.out",
+    )?;
+    run(
+        &["-w=unicode", NIHONGO],
+        "tests/expected/nihongo.txt.words_unicode.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn nihongo_graphemes() -> TestResult {
+    run(
+        &["-m", "--graphemes", NIHONGO],
+        "tests/expected/nihongo.txt.graphemes.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn nihongo_display_width() -> TestResult {
+    run(
+        &["-L", "--display-width", NIHONGO],
+        "tests/expected/nihongo.txt.display_width.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn test_all_unicode() -> TestResult {
+    run(
+        &[
+            "-l",
+            "--words=unicode",
+            "--graphemes",
+            "--display-width",
+            FOX,
+            NIHONGO,
+        ],
+        "tests/expected/all.unicode.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_words_mode() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--words=kanji", FOX])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("invalid value 'kanji'"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn test_all_json() -> TestResult {
+    run(
+        &["--format", "json", EMPTY, FOX, ATLAMAL],
+        "tests/expected/all.json.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn test_all_csv() -> TestResult {
+    run(
+        &["--format", "csv", EMPTY, FOX, ATLAMAL],
+        "tests/expected/all.csv.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn test_all_json_total_only() -> TestResult {
+    run(
+        &[
+            "--format=json",
+            "--total=only",
+            "-lwmL",
+            EMPTY,
+            FOX,
+            ATLAMAL,
+        ],
+        "tests/expected/all.json.total_only.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn json_bad_file() -> TestResult {
+    let bad = gen_bad_file();
+    let expected = format!(
+        r#"{{"counts":{{"lines":1}},"file":"{FOX}"}}
+{{"error":"No such file or directory (os error 2)","file":"{bad}"}}
+{{"total":{{"lines":1}}}}
+"#
+    );
+    Command::cargo_bin(PRG)?
+        .args(["--format=json", "-l", FOX, &bad])
+        .assert()
+        .success()
+        .stdout(expected)
+        .stderr(predicate::str::contains(bad.as_str()));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn csv_bad_file() -> TestResult {
+    let bad = gen_bad_file();
+    let expected = format!(
+        "file,lines,bytes,error\n\
+         {bad},,,No such file or directory (os error 2)\n\
+         {FOX},1,48,\n\
+         ,1,48,\n"
+    );
+    Command::cargo_bin(PRG)?
+        .args(["--format=csv", "-lc", &bad, FOX])
+        .assert()
+        .success()
+        .stdout(expected);
+    Ok(())
+}